@@ -1,9 +1,17 @@
-use shared::codes::{HidEvent, MouseButtons, ScanCode};
+use shared::codes::{
+    ChannelData, EmulatorStatus, HidEvent, LockState, MouseButtons, PenState, ScanCode,
+    TouchContact,
+};
+use shared::state::InputState;
 use softbuffer::{Context, Surface};
+
+use crate::profile::PointerMode;
+use crate::stream::RemoteEvent;
 use std::num::NonZeroU32;
 use tokio::sync::mpsc::Sender;
 use winit::application::ApplicationHandler;
-use winit::event::{ElementState, WindowEvent};
+use winit::dpi::PhysicalPosition;
+use winit::event::{ElementState, Force, Ime, Touch, TouchPhase, WindowEvent};
 use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
 use winit::keyboard::{KeyCode, PhysicalKey};
 use winit::window::{Window, WindowId};
@@ -14,31 +22,141 @@ pub struct App {
     window: Option<std::rc::Rc<Window>>,
     context: Option<Context<std::rc::Rc<Window>>>,
     surface: Option<Surface<std::rc::Rc<Window>, std::rc::Rc<Window>>>,
-    tx: Sender<HidEvent>,
+    tx: Sender<ChannelData>,
+    // Keys pressed while the IME has a preedit open belong to the composition and are
+    // sent as text once it's committed
+    composing: bool,
+    // winit touch ids currently mapped to each digitizer contact slot
+    touch_slots: [Option<u64>; 10],
+    pointer: PointerMode,
+    status: EmulatorStatus,
+    locks: Option<LockState>,
 }
 
 impl App {
-    pub fn new(tx: Sender<HidEvent>) -> Self {
+    pub fn new(tx: Sender<ChannelData>, pointer: PointerMode) -> Self {
         Self {
             window: None,
             context: None,
             surface: None,
             tx,
+            composing: false,
+            touch_slots: [None; 10],
+            pointer,
+            status: EmulatorStatus::Connected,
+            locks: None,
+        }
+    }
+
+    fn title(&self) -> String {
+        let mut title = status_title(&self.status);
+        if let Some(locks) = self.locks {
+            for (on, name) in [
+                (locks.caps, "Caps Lock"),
+                (locks.num, "Num Lock"),
+                (locks.scroll, "Scroll Lock"),
+            ] {
+                if on {
+                    title.push_str(" | ");
+                    title.push_str(name);
+                }
+            }
         }
+        title
+    }
+
+    /// Scales a position inside the window to the 0..=u16::MAX range used on the wire
+    fn normalize(&self, position: PhysicalPosition<f64>) -> Option<(u16, u16)> {
+        let size = self.window.as_ref()?.inner_size();
+        if size.width == 0 || size.height == 0 {
+            return None;
+        }
+        let scale = |pos: f64, len: u32| (pos / len as f64).clamp(0.0, 1.0) * u16::MAX as f64;
+        Some((
+            scale(position.x, size.width) as u16,
+            scale(position.y, size.height) as u16,
+        ))
+    }
+
+    fn handle_touch(&mut self, touch: Touch) {
+        let Some((x, y)) = self.normalize(touch.location) else {
+            return;
+        };
+        let down = matches!(touch.phase, TouchPhase::Started | TouchPhase::Moved);
+
+        // Only styluses report an altitude, which is what tells them apart from fingers.
+        // winit only has it on iOS, desktop tablets need the evdev capture
+        if let Some(Force::Calibrated {
+            altitude_angle: Some(altitude),
+            ..
+        }) = touch.force
+        {
+            // winit gives no azimuth, so all of the tilt is reported on the x axis
+            let tilt = 90.0 - altitude.to_degrees().clamp(0.0, 90.0);
+            let pressure = touch.force.map_or(0.0, |force| force.normalized());
+            self.send(HidEvent::Pen(PenState {
+                in_range: down,
+                tip: down,
+                x,
+                y,
+                pressure: (pressure.clamp(0.0, 1.0) * u16::MAX as f64) as u16,
+                tilt_x: tilt as i8,
+                tilt_y: 0,
+            }));
+            return;
+        }
+
+        let slot = match self.touch_slots.iter().position(|id| *id == Some(touch.id)) {
+            Some(slot) => slot,
+            None if down => match self.touch_slots.iter().position(Option::is_none) {
+                Some(slot) => {
+                    self.touch_slots[slot] = Some(touch.id);
+                    slot
+                }
+                // Out of contact slots, the extra finger is ignored
+                None => return,
+            },
+            None => return,
+        };
+        if !down {
+            self.touch_slots[slot] = None;
+        }
+        self.send(HidEvent::Touch(TouchContact {
+            id: slot as u8,
+            touching: down,
+            x,
+            y,
+        }));
+    }
+
+    fn send(&self, hid_event: HidEvent) {
+        self.tx.blocking_send(ChannelData::Hid(hid_event)).unwrap();
+    }
+}
+
+fn status_title(status: &EmulatorStatus) -> String {
+    match status {
+        EmulatorStatus::Connected => "It works!".to_string(),
+        EmulatorStatus::Searching => "Remote keyboard device disconnected".to_string(),
+        EmulatorStatus::Error(e) => format!("Remote keyboard device error: {}", e),
     }
 }
 
 // The main application logic, implemented as a trait
-impl ApplicationHandler for App {
+impl ApplicationHandler<RemoteEvent> for App {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
         if self.window.is_none() {
             println!("resumed: creating window");
-            let window_attributes = Window::default_attributes().with_title("It works!");
+            let window_attributes = Window::default_attributes().with_title(self.title());
             let window = std::rc::Rc::new(event_loop.create_window(window_attributes).unwrap());
-            window
-                .set_cursor_grab(winit::window::CursorGrabMode::Locked)
-                .unwrap();
-            window.set_cursor_visible(false);
+            // In absolute mode the cursor has to move freely over the window
+            if self.pointer == PointerMode::Relative {
+                window
+                    .set_cursor_grab(winit::window::CursorGrabMode::Locked)
+                    .unwrap();
+                window.set_cursor_visible(false);
+            }
+            window.set_ime_allowed(true);
             self.window = Some(window.clone());
 
             let context = Context::new(window.clone()).unwrap();
@@ -49,6 +167,17 @@ impl ApplicationHandler for App {
         }
     }
 
+    /// Updates about the target from the server, sent through an `EventLoopProxy`
+    fn user_event(&mut self, _event_loop: &ActiveEventLoop, event: RemoteEvent) {
+        match event {
+            RemoteEvent::Status(status) => self.status = status,
+            RemoteEvent::Locks(locks) => self.locks = Some(locks),
+        }
+        if let Some(window) = &self.window {
+            window.set_title(&self.title());
+        }
+    }
+
     fn about_to_wait(&mut self, _event_loop: &ActiveEventLoop) {
         // Unless you handle RedrawRequested, your window will not be redrawn.
         // if let Some(window) = &self.window {
@@ -99,9 +228,35 @@ impl ApplicationHandler for App {
                 event,
                 is_synthetic,
             } => {
+                if self.composing {
+                    return;
+                }
                 if let PhysicalKey::Code(code) = event.physical_key {
                     let scan_code = HidEvent::Key(ScanCode::new(code, event.state));
-                    self.tx.blocking_send(scan_code).unwrap();
+                    self.send(scan_code);
+                }
+            }
+            WindowEvent::Ime(ime) => match ime {
+                Ime::Preedit(text, _) => self.composing = !text.is_empty(),
+                Ime::Commit(text) => {
+                    self.composing = false;
+                    for chunk in ChannelData::text(&text) {
+                        self.tx.blocking_send(chunk).unwrap();
+                    }
+                }
+                Ime::Enabled | Ime::Disabled => self.composing = false,
+            },
+            WindowEvent::Touch(touch) => self.handle_touch(touch),
+            // Releases can go missing around a focus change, so capture starts over with
+            // nothing held whenever focus comes or goes
+            WindowEvent::Focused(_) => {
+                self.tx
+                    .blocking_send(ChannelData::Snapshot(InputState::new()))
+                    .unwrap();
+            }
+            WindowEvent::CursorMoved { position, .. } if self.pointer == PointerMode::Absolute => {
+                if let Some((x, y)) = self.normalize(position) {
+                    self.send(HidEvent::MouseAbsolute { x, y });
                 }
             }
             WindowEvent::MouseInput {
@@ -110,7 +265,7 @@ impl ApplicationHandler for App {
                 button,
             } => {
                 let code = HidEvent::MouseButton(MouseButtons::new(button, state));
-                self.tx.blocking_send(code).unwrap();
+                self.send(code);
             }
             WindowEvent::MouseWheel {
                 device_id,
@@ -121,7 +276,7 @@ impl ApplicationHandler for App {
                     winit::event::MouseScrollDelta::LineDelta(_, i) => i as i32,
                     winit::event::MouseScrollDelta::PixelDelta(_) => 0,
                 };
-                self.tx.blocking_send(HidEvent::MouseScroll(delta)).unwrap();
+                self.send(HidEvent::MouseScroll(delta));
             }
             _ => {}
         }
@@ -134,10 +289,10 @@ impl ApplicationHandler for App {
         event: winit::event::DeviceEvent,
     ) {
         match event {
-            winit::event::DeviceEvent::MouseMotion { delta } => {
-                self.tx
-                    .blocking_send(HidEvent::MouseDelta(delta.0 as i32, delta.1 as i32))
-                    .unwrap();
+            winit::event::DeviceEvent::MouseMotion { delta }
+                if self.pointer == PointerMode::Relative =>
+            {
+                self.send(HidEvent::MouseDelta(delta.0 as i32, delta.1 as i32));
             }
             _ => {}
         }
@@ -1,7 +1,13 @@
 use client::app::App;
+#[cfg(target_os = "linux")]
+use client::capture::EvdevCapture;
 use client::display::DisplayControl;
-use client::stream::{Audio, Inputs, SharedSender};
+use client::gamepad::Gamepads;
+use client::profile::{PROFILES_PATH, Profile};
+use client::stream::{Audio, Inputs, RemoteEvent, SharedSender};
 use shared::codes::HidEvent;
+use shared::layers::Layers;
+use shared::tap_hold::TapHold;
 use std::net::{Ipv4Addr, SocketAddrV4, UdpSocket};
 use std::str::FromStr;
 use std::thread;
@@ -9,12 +15,31 @@ use tokio::join;
 use tokio::net::TcpStream;
 use tokio::runtime::Builder;
 use tokio::sync::mpsc::{self, channel};
+#[cfg(target_os = "linux")]
+use tokio::sync::watch;
 use winit::event_loop::{ControlFlow, EventLoop};
 
 fn main() {
     let mac_address: [u8; 6] = [0xD8, 0x5E, 0xD3, 0x85, 0x95, 0xEB];
     wake_computer(mac_address).unwrap();
+    // `--profile <name>` picks the settings for the target from the profiles file
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let profile = match args.iter().position(|arg| arg == "--profile") {
+        Some(i) if i + 1 < args.len() => {
+            let name = args.drain(i..i + 2).nth(1).unwrap();
+            Profile::load(PROFILES_PATH, &name)
+        }
+        _ => Profile::load(PROFILES_PATH, "default"),
+    };
+
     let (hid_tx, hid_rx) = channel(128);
+    let tap_hold = TapHold::new(profile.tap_hold.clone());
+    let layers = Layers::new(profile.keymap.clone());
+    let remap = profile.remap.clone();
+    let (events_tx, mut events_rx) = channel::<RemoteEvent>(8);
+
+    let gamepad_tx = hid_tx.clone();
+    thread::spawn(move || Gamepads::new(gamepad_tx).handle_loop());
 
     thread::spawn(move || {
         let rt = Builder::new_multi_thread().enable_all().build().unwrap();
@@ -38,8 +63,8 @@ fn main() {
 
             let shared_sender = SharedSender::new(wifi_tx, write_rx);
 
-            let inputs = Inputs::new(write_tx.clone(), hid_rx);
-            let audio = Audio::new(wifi_rx);
+            let inputs = Inputs::new(write_tx.clone(), hid_rx, tap_hold, layers, remap);
+            let audio = Audio::new(wifi_rx, events_tx);
             let display_control = DisplayControl::new("/tmp/stream_temp", "G274QPF E2", write_tx);
 
             let shared_handle = tokio::spawn(shared_sender.write_loop());
@@ -49,9 +74,46 @@ fn main() {
             join!(shared_handle, input_handle, display_handle, audio_handle);
         });
     });
-    let event_loop = EventLoop::new().unwrap();
+
+    // `--headless /dev/input/eventX ...` captures the given devices directly instead of
+    // opening a capture window
+    #[cfg(target_os = "linux")]
+    {
+        if args.first().is_some_and(|arg| arg == "--headless") {
+            let rt = Builder::new_current_thread().enable_all().build().unwrap();
+            rt.block_on(async {
+                let (locks_tx, locks_rx) = watch::channel(None);
+                tokio::spawn(async move {
+                    while let Some(event) = events_rx.recv().await {
+                        match event {
+                            RemoteEvent::Status(status) => {
+                                println!("Remote device status: {:?}", status)
+                            }
+                            RemoteEvent::Locks(locks) => {
+                                println!("Remote locks: {:?}", locks);
+                                locks_tx.send_replace(Some(locks));
+                            }
+                        }
+                    }
+                });
+                let capture = EvdevCapture::new(&args[1..], hid_tx).unwrap();
+                capture.handle_loop(locks_rx, profile.locks).await;
+            });
+            return;
+        }
+    }
+
+    let event_loop = EventLoop::<RemoteEvent>::with_user_event().build().unwrap();
     event_loop.set_control_flow(ControlFlow::Wait);
-    let mut app = App::new(hid_tx);
+    let proxy = event_loop.create_proxy();
+    thread::spawn(move || {
+        while let Some(event) = events_rx.blocking_recv() {
+            if proxy.send_event(event).is_err() {
+                break;
+            }
+        }
+    });
+    let mut app = App::new(hid_tx, profile.pointer);
     event_loop.run_app(&mut app).unwrap();
 }
 
@@ -0,0 +1,490 @@
+use std::{collections::HashMap, io, path::Path};
+
+use evdev::{
+    AbsInfo, AbsoluteAxisCode, AttributeSetRef, Device, EventStream, EventSummary, EventType,
+    InputEvent, KeyCode as EvdevKey, LedCode, RelativeAxisCode, SynchronizationCode,
+};
+use shared::{
+    codes::{ChannelData, HidEvent, LockState, MouseButtons, PenState, ScanCode},
+    state::InputState,
+};
+use tokio::{
+    select,
+    sync::{mpsc::Sender, watch},
+    task::JoinSet,
+};
+use winit::{event::ElementState, keyboard::KeyCode};
+
+use crate::profile::LockFeedback;
+
+/// Captures input straight from `/dev/input/event*` nodes instead of a focused window.
+///
+/// Every device is grabbed exclusively, so the local machine stops seeing its input
+/// for as long as the capture is running. Graphics tablets are forwarded as a pen, which
+/// is the only way to get pen input on desktop clients.
+pub struct EvdevCapture {
+    streams: Vec<EventStream>,
+    tx: Sender<ChannelData>,
+    // What the first keyboard's LEDs showed before it was grabbed
+    local_locks: Option<LockState>,
+}
+
+impl EvdevCapture {
+    pub fn new<P: AsRef<Path>>(paths: &[P], tx: Sender<ChannelData>) -> io::Result<Self> {
+        let mut streams = Vec::with_capacity(paths.len());
+        let mut local_locks = None;
+        for path in paths {
+            let mut device = Device::open(path)?;
+            println!(
+                "Grabbing {}: {}",
+                path.as_ref().display(),
+                device.name().unwrap_or("unknown device")
+            );
+            if local_locks.is_none() && has_lock_leds(&device) {
+                local_locks = Some(lock_state(&device.get_led_state()?));
+            }
+            device.grab()?;
+            streams.push(device.into_event_stream()?);
+        }
+        Ok(Self {
+            streams,
+            tx,
+            local_locks,
+        })
+    }
+
+    /// Captures until every device is gone, handling the target's lock state from
+    /// `locks_rx` according to `feedback`
+    pub async fn handle_loop(
+        self,
+        locks_rx: watch::Receiver<Option<LockState>>,
+        feedback: LockFeedback,
+    ) {
+        // Whatever the server still holds from before the grab is released
+        if self
+            .tx
+            .send(ChannelData::Snapshot(InputState::new()))
+            .await
+            .is_err()
+        {
+            return;
+        }
+        let mut set = JoinSet::new();
+        if let (LockFeedback::Sync, Some(local)) = (feedback, self.local_locks) {
+            set.spawn(sync_locks(local, locks_rx.clone(), self.tx.clone()));
+        }
+        for stream in self.streams {
+            let locks_rx = (feedback != LockFeedback::Show).then(|| locks_rx.clone());
+            set.spawn(device_loop(stream, self.tx.clone(), locks_rx));
+        }
+        while let Some(res) = set.join_next().await {
+            if let Ok(Err(e)) = res {
+                println!("Capture device closed: {}", e);
+            }
+        }
+    }
+}
+
+fn has_lock_leds(device: &Device) -> bool {
+    device
+        .supported_leds()
+        .is_some_and(|leds| leds.contains(LedCode::LED_CAPSL))
+}
+
+fn lock_state(leds: &AttributeSetRef<LedCode>) -> LockState {
+    LockState {
+        caps: leds.contains(LedCode::LED_CAPSL),
+        num: leds.contains(LedCode::LED_NUML),
+        scroll: leds.contains(LedCode::LED_SCROLLL),
+    }
+}
+
+fn set_leds(device: &mut Device, locks: LockState) -> io::Result<()> {
+    let led = |code: LedCode, on: bool| InputEvent::new(EventType::LED.0, code.0, on as i32);
+    device.send_events(&[
+        led(LedCode::LED_CAPSL, locks.caps),
+        led(LedCode::LED_NUML, locks.num),
+        led(LedCode::LED_SCROLLL, locks.scroll),
+    ])
+}
+
+/// Pen of a graphics tablet, whose axes are scaled from the ranges the device reports
+struct Tablet {
+    axes: HashMap<AbsoluteAxisCode, AbsInfo>,
+    pen: PenState,
+    // Whether `pen` changed since the last SYN_REPORT
+    changed: bool,
+}
+
+impl Tablet {
+    /// `None` unless the device has a pen tool and an absolute position
+    fn new(device: &Device) -> io::Result<Option<Self>> {
+        let has_pen = device
+            .supported_keys()
+            .is_some_and(|keys| keys.contains(EvdevKey::BTN_TOOL_PEN));
+        let axes: HashMap<_, _> = device.get_absinfo()?.collect();
+        if !has_pen
+            || !axes.contains_key(&AbsoluteAxisCode::ABS_X)
+            || !axes.contains_key(&AbsoluteAxisCode::ABS_Y)
+        {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            axes,
+            pen: PenState {
+                in_range: false,
+                tip: false,
+                x: 0,
+                y: 0,
+                pressure: 0,
+                tilt_x: 0,
+                tilt_y: 0,
+            },
+            changed: false,
+        }))
+    }
+
+    /// `value` of `axis` from its range onto `0..=u16::MAX`
+    fn scale(&self, axis: AbsoluteAxisCode, value: i32) -> u16 {
+        let Some(info) = self.axes.get(&axis) else {
+            return 0;
+        };
+        let range = (info.maximum() as i64 - info.minimum() as i64).max(1);
+        let value = (value as i64 - info.minimum() as i64).clamp(0, range);
+        (value * u16::MAX as i64 / range) as u16
+    }
+
+    /// Tilt in degrees. The resolution is in units per radian, devices without one are
+    /// taken to report degrees
+    fn tilt(&self, axis: AbsoluteAxisCode, value: i32) -> i8 {
+        let resolution = self.axes.get(&axis).map_or(0, AbsInfo::resolution);
+        let degrees = match resolution {
+            0 => value as f64,
+            resolution => (value as f64 / resolution as f64).to_degrees(),
+        };
+        degrees.clamp(-90.0, 90.0) as i8
+    }
+
+    /// Takes in a pen key, false for anything else
+    fn key(&mut self, key: EvdevKey, value: i32) -> bool {
+        match key {
+            EvdevKey::BTN_TOOL_PEN => self.pen.in_range = value != 0,
+            EvdevKey::BTN_TOUCH => self.pen.tip = value != 0,
+            _ => return false,
+        }
+        self.changed = true;
+        true
+    }
+
+    /// Takes in a pen axis, false for anything else
+    fn axis(&mut self, axis: AbsoluteAxisCode, value: i32) -> bool {
+        match axis {
+            AbsoluteAxisCode::ABS_X => self.pen.x = self.scale(axis, value),
+            AbsoluteAxisCode::ABS_Y => self.pen.y = self.scale(axis, value),
+            AbsoluteAxisCode::ABS_PRESSURE => self.pen.pressure = self.scale(axis, value),
+            AbsoluteAxisCode::ABS_TILT_X => self.pen.tilt_x = self.tilt(axis, value),
+            AbsoluteAxisCode::ABS_TILT_Y => self.pen.tilt_y = self.tilt(axis, value),
+            _ => return false,
+        }
+        self.changed = true;
+        true
+    }
+
+    /// The pen's state once a frame changed it
+    fn report(&mut self) -> Option<HidEvent> {
+        let changed = std::mem::take(&mut self.changed);
+        changed.then_some(HidEvent::Pen(self.pen))
+    }
+}
+
+/// Toggles the target's locks to match `local` as soon as the target's are known. The taps
+/// skip the client's remapping so they always hit the lock keys themselves
+async fn sync_locks(
+    local: LockState,
+    mut locks_rx: watch::Receiver<Option<LockState>>,
+    tx: Sender<ChannelData>,
+) -> io::Result<()> {
+    let remote = match locks_rx.wait_for(Option::is_some).await {
+        Ok(remote) => remote.unwrap(),
+        Err(_) => return Ok(()),
+    };
+    for (differs, code) in [
+        (local.caps != remote.caps, KeyCode::CapsLock),
+        (local.num != remote.num, KeyCode::NumLock),
+        (local.scroll != remote.scroll, KeyCode::ScrollLock),
+    ] {
+        if !differs {
+            continue;
+        }
+        for dir in [ElementState::Pressed, ElementState::Released] {
+            let tap = ChannelData::Unmapped(HidEvent::Key(ScanCode::new(code, dir)));
+            if tx.send(tap).await.is_err() {
+                return Ok(());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Forwards one device's input. With `locks_rx` the device's LEDs follow the target's
+async fn device_loop(
+    mut stream: EventStream,
+    tx: Sender<ChannelData>,
+    mut locks_rx: Option<watch::Receiver<Option<LockState>>>,
+) -> io::Result<()> {
+    if !has_lock_leds(stream.device()) {
+        locks_rx = None;
+    }
+    let mut tablet = Tablet::new(stream.device())?;
+    // Relative motion arrives as separate X and Y events, so they're accumulated until
+    // the SYN_REPORT that closes the frame and sent as a single delta. Pen frames are
+    // sent whole the same way
+    let (mut dx, mut dy) = (0, 0);
+    loop {
+        let event = match &mut locks_rx {
+            Some(locks_rx) => select! {
+                event = stream.next_event() => event?,
+                locks = async {
+                    match locks_rx.changed().await {
+                        Ok(()) => *locks_rx.borrow_and_update(),
+                        // Nothing more to mirror, keep capturing
+                        Err(_) => std::future::pending().await,
+                    }
+                } => {
+                    if let Some(locks) = locks {
+                        set_leds(stream.device_mut(), locks)?;
+                    }
+                    continue;
+                }
+            },
+            None => stream.next_event().await?,
+        };
+        let hid_event = match event.destructure() {
+            EventSummary::Key(_, key, value)
+                if tablet.as_mut().is_some_and(|tablet| tablet.key(key, value)) =>
+            {
+                continue;
+            }
+            EventSummary::AbsoluteAxis(_, axis, value)
+                if tablet
+                    .as_mut()
+                    .is_some_and(|tablet| tablet.axis(axis, value)) =>
+            {
+                continue;
+            }
+            EventSummary::Key(_, key, value) => {
+                let dir = match value {
+                    0 => ElementState::Released,
+                    1 => ElementState::Pressed,
+                    // Autorepeat is left to the remote machine
+                    _ => continue,
+                };
+                if let Some(button) = MouseButtons::from_evdev(key, dir) {
+                    HidEvent::MouseButton(button)
+                } else if let Some(scan_code) = ScanCode::from_evdev(key, dir) {
+                    HidEvent::Key(scan_code)
+                } else {
+                    continue;
+                }
+            }
+            EventSummary::RelativeAxis(_, RelativeAxisCode::REL_X, value) => {
+                dx += value;
+                continue;
+            }
+            EventSummary::RelativeAxis(_, RelativeAxisCode::REL_Y, value) => {
+                dy += value;
+                continue;
+            }
+            EventSummary::RelativeAxis(_, RelativeAxisCode::REL_WHEEL, value) => {
+                HidEvent::MouseScroll(value)
+            }
+            EventSummary::Synchronization(_, SynchronizationCode::SYN_REPORT, _) => {
+                if let Some(pen) = tablet.as_mut().and_then(Tablet::report)
+                    && tx.send(ChannelData::Hid(pen)).await.is_err()
+                {
+                    return Ok(());
+                }
+                if dx == 0 && dy == 0 {
+                    continue;
+                }
+                let delta = HidEvent::MouseDelta(dx, dy);
+                (dx, dy) = (0, 0);
+                delta
+            }
+            _ => continue,
+        };
+        if tx.send(ChannelData::Hid(hid_event)).await.is_err() {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, time::Duration};
+
+    use evdev::{AttributeSet, UinputAbsSetup, uinput::VirtualDevice};
+    use tokio::{sync::mpsc, time::timeout};
+    use winit::event::MouseButton;
+
+    use super::*;
+
+    fn dev_node(device: &mut VirtualDevice) -> PathBuf {
+        // The node shows up once udev has seen the device
+        for _ in 0..50 {
+            if let Some(Ok(path)) = device.enumerate_dev_nodes_blocking().unwrap().next() {
+                return path;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        panic!("no event node for the virtual device");
+    }
+
+    async fn next(rx: &mut mpsc::Receiver<ChannelData>) -> ChannelData {
+        timeout(Duration::from_secs(2), rx.recv())
+            .await
+            .expect("capture went quiet")
+            .expect("capture stopped")
+    }
+
+    async fn next_hid(rx: &mut mpsc::Receiver<ChannelData>) -> HidEvent {
+        match next(rx).await {
+            ChannelData::Hid(hid_event) => hid_event,
+            _ => panic!("expected a HID event"),
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs write access to /dev/uinput"]
+    async fn captures_virtual_keyboard_and_mouse() {
+        let builder = VirtualDevice::builder().unwrap();
+        let mut keyboard = builder
+            .name("capture test keyboard")
+            .with_keys(&AttributeSet::from_iter([EvdevKey::KEY_A]))
+            .unwrap()
+            .build()
+            .unwrap();
+        let mut mouse = VirtualDevice::builder()
+            .unwrap()
+            .name("capture test mouse")
+            .with_keys(&AttributeSet::from_iter([EvdevKey::BTN_LEFT]))
+            .unwrap()
+            .with_relative_axes(&AttributeSet::from_iter([
+                RelativeAxisCode::REL_X,
+                RelativeAxisCode::REL_Y,
+                RelativeAxisCode::REL_WHEEL,
+            ]))
+            .unwrap()
+            .build()
+            .unwrap();
+        let paths = [dev_node(&mut keyboard), dev_node(&mut mouse)];
+
+        let (tx, mut rx) = mpsc::channel(16);
+        let capture = EvdevCapture::new(&paths, tx).unwrap();
+        let (_locks_tx, locks_rx) = watch::channel(None);
+        let task = tokio::spawn(capture.handle_loop(locks_rx, LockFeedback::Show));
+
+        match next(&mut rx).await {
+            ChannelData::Snapshot(snapshot) => assert_eq!(snapshot, InputState::new()),
+            _ => panic!("capture has to start with an empty snapshot"),
+        }
+
+        let key = |value| InputEvent::new(EventType::KEY.0, EvdevKey::KEY_A.0, value);
+        keyboard.emit(&[key(1)]).unwrap();
+        keyboard.emit(&[key(2)]).unwrap();
+        keyboard.emit(&[key(0)]).unwrap();
+        assert_eq!(
+            next_hid(&mut rx).await,
+            HidEvent::Key(ScanCode::new(KeyCode::KeyA, ElementState::Pressed))
+        );
+        // The autorepeat is skipped
+        assert_eq!(
+            next_hid(&mut rx).await,
+            HidEvent::Key(ScanCode::new(KeyCode::KeyA, ElementState::Released))
+        );
+
+        let rel =
+            |axis: RelativeAxisCode, value| InputEvent::new(EventType::RELATIVE.0, axis.0, value);
+        mouse
+            .emit(&[
+                rel(RelativeAxisCode::REL_X, 5),
+                rel(RelativeAxisCode::REL_Y, -3),
+            ])
+            .unwrap();
+        assert_eq!(next_hid(&mut rx).await, HidEvent::MouseDelta(5, -3));
+
+        let button = InputEvent::new(EventType::KEY.0, EvdevKey::BTN_LEFT.0, 1);
+        mouse.emit(&[button]).unwrap();
+        assert_eq!(
+            next_hid(&mut rx).await,
+            HidEvent::MouseButton(MouseButtons::new(MouseButton::Left, ElementState::Pressed))
+        );
+
+        mouse.emit(&[rel(RelativeAxisCode::REL_WHEEL, 1)]).unwrap();
+        assert_eq!(next_hid(&mut rx).await, HidEvent::MouseScroll(1));
+
+        task.abort();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs write access to /dev/uinput"]
+    async fn captures_virtual_tablet_pen() {
+        let builder = VirtualDevice::builder().unwrap();
+        let axis = |axis, min, max, resolution| {
+            UinputAbsSetup::new(axis, AbsInfo::new(0, min, max, 0, 0, resolution))
+        };
+        let mut tablet = builder
+            .name("capture test tablet")
+            .with_keys(&AttributeSet::from_iter([
+                EvdevKey::BTN_TOOL_PEN,
+                EvdevKey::BTN_TOUCH,
+            ]))
+            .unwrap()
+            .with_absolute_axis(&axis(AbsoluteAxisCode::ABS_X, 0, 1000, 0))
+            .unwrap()
+            .with_absolute_axis(&axis(AbsoluteAxisCode::ABS_Y, 0, 1000, 0))
+            .unwrap()
+            .with_absolute_axis(&axis(AbsoluteAxisCode::ABS_PRESSURE, 0, 2047, 0))
+            .unwrap()
+            .with_absolute_axis(&axis(AbsoluteAxisCode::ABS_TILT_X, -64, 63, 57))
+            .unwrap()
+            .build()
+            .unwrap();
+        let paths = [dev_node(&mut tablet)];
+
+        let (tx, mut rx) = mpsc::channel(16);
+        let capture = EvdevCapture::new(&paths, tx).unwrap();
+        let (_locks_tx, locks_rx) = watch::channel(None);
+        let task = tokio::spawn(capture.handle_loop(locks_rx, LockFeedback::Show));
+        assert!(matches!(next(&mut rx).await, ChannelData::Snapshot(_)));
+
+        let key = |key: EvdevKey, value| InputEvent::new(EventType::KEY.0, key.0, value);
+        let abs =
+            |axis: AbsoluteAxisCode, value| InputEvent::new(EventType::ABSOLUTE.0, axis.0, value);
+        tablet
+            .emit(&[
+                key(EvdevKey::BTN_TOOL_PEN, 1),
+                key(EvdevKey::BTN_TOUCH, 1),
+                abs(AbsoluteAxisCode::ABS_X, 500),
+                abs(AbsoluteAxisCode::ABS_Y, 1000),
+                abs(AbsoluteAxisCode::ABS_PRESSURE, 2047),
+                abs(AbsoluteAxisCode::ABS_TILT_X, 30),
+            ])
+            .unwrap();
+        // The whole frame is a single pen report
+        assert_eq!(
+            next_hid(&mut rx).await,
+            HidEvent::Pen(PenState {
+                in_range: true,
+                tip: true,
+                x: 32767,
+                y: u16::MAX,
+                pressure: u16::MAX,
+                tilt_x: 30,
+                tilt_y: 0,
+            })
+        );
+
+        task.abort();
+    }
+}
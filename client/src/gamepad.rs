@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use gilrs::{Axis, Button, Event, EventType, GamepadId, Gilrs};
+use shared::codes::{ChannelData, GamepadButton, GamepadState, HidEvent};
+use tokio::sync::mpsc::Sender;
+
+/// Polls every connected game controller and sends a full [`GamepadState`] whenever one
+/// of them changes
+pub struct Gamepads {
+    gilrs: Gilrs,
+    tx: Sender<ChannelData>,
+    states: HashMap<GamepadId, GamepadState>,
+}
+
+impl Gamepads {
+    pub fn new(tx: Sender<ChannelData>) -> Self {
+        Self {
+            gilrs: Gilrs::new().unwrap(),
+            tx,
+            states: HashMap::new(),
+        }
+    }
+
+    /// Blocks the calling thread, so run it on its own thread
+    pub fn handle_loop(mut self) {
+        loop {
+            let Some(Event { id: pad, event, .. }) = self.gilrs.next_event_blocking(None) else {
+                continue;
+            };
+            let state = self
+                .states
+                .entry(pad)
+                .or_insert_with(|| GamepadState::new(usize::from(pad) as u8));
+            let changed = match event {
+                EventType::ButtonPressed(button, _) => set_button(state, button, true),
+                EventType::ButtonReleased(button, _) => set_button(state, button, false),
+                EventType::ButtonChanged(Button::LeftTrigger2, value, _) => {
+                    state.left_trigger = (value * u8::MAX as f32) as u8;
+                    true
+                }
+                EventType::ButtonChanged(Button::RightTrigger2, value, _) => {
+                    state.right_trigger = (value * u8::MAX as f32) as u8;
+                    true
+                }
+                EventType::AxisChanged(axis, value, _) => {
+                    let value = (value.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+                    match axis {
+                        Axis::LeftStickX => state.left_stick.0 = value,
+                        Axis::LeftStickY => state.left_stick.1 = value,
+                        Axis::RightStickX => state.right_stick.0 = value,
+                        Axis::RightStickY => state.right_stick.1 = value,
+                        _ => continue,
+                    }
+                    true
+                }
+                EventType::Disconnected => {
+                    // Release everything on the server before forgetting the controller
+                    let id = state.id;
+                    self.states.remove(&pad);
+                    self.send(GamepadState::new(id));
+                    continue;
+                }
+                _ => false,
+            };
+            if changed {
+                let state = *state;
+                self.send(state);
+            }
+        }
+    }
+
+    fn send(&self, state: GamepadState) {
+        self.tx
+            .blocking_send(ChannelData::Hid(HidEvent::Gamepad(state)))
+            .unwrap();
+    }
+}
+
+fn set_button(state: &mut GamepadState, button: Button, pressed: bool) -> bool {
+    let button = match button {
+        Button::South => GamepadButton::South,
+        Button::East => GamepadButton::East,
+        Button::North => GamepadButton::North,
+        Button::West => GamepadButton::West,
+        Button::LeftTrigger => GamepadButton::LeftBumper,
+        Button::RightTrigger => GamepadButton::RightBumper,
+        Button::Select => GamepadButton::Select,
+        Button::Start => GamepadButton::Start,
+        Button::Mode => GamepadButton::Mode,
+        Button::LeftThumb => GamepadButton::LeftThumb,
+        Button::RightThumb => GamepadButton::RightThumb,
+        Button::DPadUp => GamepadButton::DPadUp,
+        Button::DPadDown => GamepadButton::DPadDown,
+        Button::DPadLeft => GamepadButton::DPadLeft,
+        Button::DPadRight => GamepadButton::DPadRight,
+        _ => return false,
+    };
+    state.set_button(button, pressed);
+    true
+}
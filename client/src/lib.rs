@@ -1,3 +1,7 @@
 pub mod app;
+#[cfg(target_os = "linux")]
+pub mod capture;
 pub mod display;
+pub mod gamepad;
+pub mod profile;
 pub mod stream;
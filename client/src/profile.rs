@@ -0,0 +1,85 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use serde::Deserialize;
+use shared::{layers::Keymap, remap::RemapTable, tap_hold::DualRoleKeys};
+
+/// Where profiles are read from, relative to the working directory
+pub const PROFILES_PATH: &str = "profiles.toml";
+
+/// How mouse movement is sent to the server
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PointerMode {
+    /// Deltas from the locked cursor, for games and anything else that grabs the mouse
+    #[default]
+    Relative,
+    /// Position of the cursor over the window, mapped onto the whole target screen. Keeps
+    /// VMs, remote desktops and multi-monitor targets from drifting
+    Absolute,
+}
+
+/// What the local keyboard does with the target's Caps, Num and Scroll Lock. The window
+/// can't reach the keyboard LEDs, so anything but showing needs headless capture
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LockFeedback {
+    /// Only show the target's locks
+    #[default]
+    Show,
+    /// Light the captured keyboards' LEDs the way the target has them
+    Mirror,
+    /// Like `Mirror`, but first toggle the target's locks to match the local LEDs when
+    /// capture begins
+    Sync,
+}
+
+/// Settings for one kind of target, e.g.
+///
+/// ```toml
+/// [vm]
+/// pointer = "absolute"
+/// locks = "mirror"
+///
+/// [vm.remap.keys]
+/// SuperLeft = ["ControlLeft"]
+///
+/// [vm.keymap.switches]
+/// CapsLock = { layer = 1 }
+///
+/// [vm.tap_hold.keys]
+/// Space = { tap = "Space", hold = "ShiftLeft" }
+/// ```
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub pointer: PointerMode,
+    pub locks: LockFeedback,
+    /// Layout quirks of the target, see [`RemapTable`]
+    pub remap: RemapTable,
+    /// Layers applied before `remap`, see [`Keymap`]
+    pub keymap: Keymap,
+    /// Keys that act differently on tap and hold, resolved before `keymap`, see
+    /// [`DualRoleKeys`]
+    pub tap_hold: DualRoleKeys,
+}
+
+impl Profile {
+    /// Loads the profile called `name`, falling back to the defaults if either the file or
+    /// the profile is missing
+    pub fn load<P: AsRef<Path>>(path: P, name: &str) -> Self {
+        let Ok(text) = fs::read_to_string(path) else {
+            return Self::default();
+        };
+        let mut profiles: HashMap<String, Profile> = match toml::from_str(&text) {
+            Ok(profiles) => profiles,
+            Err(e) => {
+                println!("Invalid profiles: {}", e);
+                return Self::default();
+            }
+        };
+        profiles.remove(name).unwrap_or_else(|| {
+            println!("No profile named {}, using defaults", name);
+            Self::default()
+        })
+    }
+}
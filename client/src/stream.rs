@@ -1,46 +1,140 @@
 use std::sync::Arc;
 use std::time::Duration;
 
-use bytemuck::cast_slice;
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::{BufferSize, Device, Stream, StreamConfig};
 use ringbuf::traits::{Consumer, Observer, Producer, Split};
 use ringbuf::wrap::caching::Caching;
 use ringbuf::{CachingProd, HeapRb, SharedRb};
 use serde::Serialize;
-use shared::codes::{ChannelData, HidEvent, ScanCode};
+use shared::codes::{ChannelData, EmulatorStatus, HidEvent, LockState, ScanCode, ServerData};
+use shared::layers::Layers;
+use shared::remap::RemapTable;
+use shared::state::InputState;
+use shared::tap_hold::TapHold;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::tcp::OwnedReadHalf;
+use tokio::select;
 use tokio::sync::mpsc::Sender;
+use tokio::time::{Instant, interval, sleep_until};
 use tokio::{net::tcp::OwnedWriteHalf, sync::mpsc::Receiver};
 
+/// How often the server is told everything the client holds
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(1);
+
 pub struct Inputs {
     shared_tx: Sender<Vec<u8>>,
-    data_rx: Receiver<HidEvent>,
+    data_rx: Receiver<ChannelData>,
+    tap_hold: TapHold,
+    layers: Layers,
+    remap: RemapTable,
+    // Keys and buttons held as far as the server has been told
+    held: InputState,
 }
 
 impl Inputs {
-    pub fn new(shared_tx: Sender<Vec<u8>>, data_rx: Receiver<HidEvent>) -> Self {
-        Self { shared_tx, data_rx }
+    pub fn new(
+        shared_tx: Sender<Vec<u8>>,
+        data_rx: Receiver<ChannelData>,
+        tap_hold: TapHold,
+        layers: Layers,
+        remap: RemapTable,
+    ) -> Self {
+        Self {
+            shared_tx,
+            data_rx,
+            tap_hold,
+            layers,
+            remap,
+            held: InputState::new(),
+        }
+    }
+
+    async fn send(&mut self, data: ChannelData) {
+        let mesg = bincode::serialize(&data).unwrap();
+        // Only a snapshot or text can outgrow the length prefix. What the server never
+        // got doesn't count as held, a later snapshot may fit again
+        if mesg.len() > u8::MAX as usize {
+            println!("Dropping a {} byte message, too long to send", mesg.len());
+            return;
+        }
+        match &data {
+            ChannelData::Hid(hid_event) => self.held.apply(hid_event),
+            // Sent when capture starts, nothing from before it counts as held anymore
+            ChannelData::Snapshot(snapshot) => self.held = snapshot.clone(),
+            _ => (),
+        }
+        self.shared_tx.send(mesg).await.unwrap();
     }
 
     pub async fn handle_loop(mut self) {
+        let mut snapshots = interval(SNAPSHOT_INTERVAL);
         loop {
-            let key: HidEvent = self.data_rx.recv().await.unwrap();
-            let mesg = bincode::serialize(&ChannelData::Hid(key)).unwrap();
-            self.shared_tx.send(mesg).await.unwrap();
+            let deadline = self.tap_hold.deadline().map(Instant::from_std);
+            select! {
+                data = self.data_rx.recv() => self.forward(data.unwrap()).await,
+                _ = sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {
+                    let events = self.tap_hold.timeout(std::time::Instant::now());
+                    self.emit(events).await;
+                }
+                _ = snapshots.tick() => {
+                    let snapshot = ChannelData::Snapshot(self.held.clone());
+                    self.send(snapshot).await;
+                }
+            }
         }
     }
+
+    /// Runs input from the capture through the dual-role keys, the layers and the remap
+    /// table
+    async fn forward(&mut self, data: ChannelData) {
+        match data {
+            ChannelData::Hid(hid_event) => {
+                let events = self.tap_hold.apply(hid_event, std::time::Instant::now());
+                self.emit(events).await;
+            }
+            ChannelData::Unmapped(hid_event) => self.send(ChannelData::Hid(hid_event)).await,
+            // Capture is starting over, layer keys may have been let go unseen
+            ChannelData::Snapshot(_) => {
+                self.tap_hold.release_all();
+                self.layers.release_all();
+                self.send(data).await;
+            }
+            data => self.send(data).await,
+        }
+    }
+
+    /// Sends what the dual-role keys let out through the layers and the remap table
+    async fn emit(&mut self, events: Vec<HidEvent>) {
+        let events: Vec<HidEvent> = events
+            .into_iter()
+            .flat_map(|hid_event| self.layers.apply(hid_event))
+            .flat_map(|hid_event| self.remap.apply(hid_event))
+            .collect();
+        for hid_event in events {
+            self.send(ChannelData::Hid(hid_event)).await;
+        }
+    }
+}
+
+/// Everything the server reports about the target besides audio
+#[derive(Debug, Clone)]
+pub enum RemoteEvent {
+    Status(EmulatorStatus),
+    Locks(LockState),
 }
 
+/// Reads everything the server sends, playing audio and passing the rest on to
+/// `events_tx`
 pub struct Audio {
     wifi_rx: OwnedReadHalf,
     audio_tx: CachingProd<Arc<HeapRb<f32>>>,
+    events_tx: Sender<RemoteEvent>,
     stream: Stream,
 }
 
 impl Audio {
-    pub fn new(wifi_rx: OwnedReadHalf) -> Self {
+    pub fn new(wifi_rx: OwnedReadHalf, events_tx: Sender<RemoteEvent>) -> Self {
         let (audio_tx, mut consumer) = HeapRb::<f32>::new(44100).split();
 
         let host = cpal::default_host();
@@ -87,16 +181,29 @@ impl Audio {
         Self {
             wifi_rx,
             audio_tx,
+            events_tx,
             stream,
         }
     }
 
     pub async fn handle_loop(mut self) {
-        let mut buf = [0u8; 128 * 2 * 4];
+        let mut buf = Vec::new();
         loop {
+            let size = self.wifi_rx.read_u32_le().await.unwrap() as usize;
+            buf.resize(size, 0);
             self.wifi_rx.read_exact(&mut buf).await.unwrap();
-            let f_slice: &[f32] = cast_slice(&buf);
-            self.audio_tx.push_slice(f_slice);
+            match bincode::deserialize::<ServerData>(&buf).unwrap() {
+                ServerData::Audio(samples) => {
+                    self.audio_tx.push_slice(&samples);
+                }
+                // Nobody might be showing these, which is fine
+                ServerData::Status(status) => {
+                    let _ = self.events_tx.send(RemoteEvent::Status(status)).await;
+                }
+                ServerData::Locks(locks) => {
+                    let _ = self.events_tx.send(RemoteEvent::Locks(locks)).await;
+                }
+            }
         }
     }
 }
@@ -124,3 +231,72 @@ impl SharedSender {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use shared::layers::Keymap;
+    use shared::remap::Output;
+    use shared::tap_hold::{DualRole, DualRoleKeys};
+    use tokio::sync::mpsc;
+    use tokio::time::timeout;
+    use winit::event::ElementState;
+    use winit::keyboard::KeyCode;
+
+    use super::*;
+
+    fn key(code: KeyCode, dir: ElementState) -> HidEvent {
+        HidEvent::Key(ScanCode::new(code, dir))
+    }
+
+    /// Next event sent to the server, skipping the periodic snapshots
+    async fn next_hid(shared_rx: &mut Receiver<Vec<u8>>) -> HidEvent {
+        loop {
+            let mesg = timeout(Duration::from_secs(1), shared_rx.recv())
+                .await
+                .expect("nothing sent")
+                .unwrap();
+            match bincode::deserialize(&mesg).unwrap() {
+                ChannelData::Hid(hid_event) => return hid_event,
+                ChannelData::Snapshot(_) => (),
+                _ => panic!("expected a HID event"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn unmapped_events_skip_remapping() {
+        let (shared_tx, mut shared_rx) = mpsc::channel(10);
+        let (data_tx, data_rx) = mpsc::channel(10);
+        let mut dual_role = DualRoleKeys::default();
+        let role = DualRole {
+            tap: KeyCode::Escape,
+            hold: KeyCode::ControlLeft,
+        };
+        dual_role.keys.insert(KeyCode::CapsLock, role);
+        let mut remap = RemapTable::new();
+        remap.map_key(KeyCode::NumLock, vec![Output::Key(KeyCode::KeyN)]);
+        let inputs = Inputs::new(
+            shared_tx,
+            data_rx,
+            TapHold::new(dual_role),
+            Layers::new(Keymap::default()),
+            remap,
+        );
+        tokio::spawn(inputs.handle_loop());
+
+        for code in [KeyCode::CapsLock, KeyCode::NumLock] {
+            for dir in [ElementState::Pressed, ElementState::Released] {
+                let tap = ChannelData::Unmapped(key(code, dir));
+                data_tx.send(tap).await.unwrap();
+                assert_eq!(next_hid(&mut shared_rx).await, key(code, dir));
+            }
+        }
+        // The same key from the keyboard still goes through the remap table
+        let press = ChannelData::Hid(key(KeyCode::NumLock, ElementState::Pressed));
+        data_tx.send(press).await.unwrap();
+        assert_eq!(
+            next_hid(&mut shared_rx).await,
+            key(KeyCode::KeyN, ElementState::Pressed)
+        );
+    }
+}
@@ -1,17 +1,91 @@
 use std::sync::Arc;
 
-use anyhow::{Ok, Result, anyhow};
-use server::stream::{Audio, DisplayControl, Inputs};
+use anyhow::{Result, anyhow};
+use server::stream::{Audio, DisplayControl, Inputs, LockForwarder, SharedSender, StatusForwarder};
+#[cfg(target_os = "linux")]
+use shared::emulator::{UinputEmulator, UinputGamepads};
 use shared::{
     codes::HidEvent,
-    emulator::{HidEmulator, WinputEmulator},
+    emulator::{CompositeEmulator, Emulator, EnigoEmulator, HidEmulator, WinputEmulator},
+    pacing::{PacedEmulator, Pacing},
+    report::ReportMode,
+    state::InputState,
 };
 use tokio::{join, net::TcpListener, select, sync::mpsc};
 
 #[tokio::main]
 async fn main() {
     let addr = "192.168.10.3:8080";
-    let emulator = Arc::new(HidEmulator::new(0xa56, 0xa56, 1));
+    // `--enigo` and `--uinput` drive the OS input stack directly for servers without the
+    // USB dongle. `--boot` sends standard boot protocol reports for stock HID proxy boards
+    // and `--nkro` sends bitmap keyboard reports when the firmware supports them. Both
+    // write to interfaces 0 and 1 and let their descriptors decide which gets the keyboard
+    // and which the mouse.
+    // `--fallback` uses the dongle while it's plugged in and enigo otherwise. `--slow`
+    // can follow any of them to space out reports for BIOS screens and slow KVMs
+    match std::env::args().nth(1).as_deref() {
+        Some("--enigo") => match EnigoEmulator::new() {
+            Ok(emulator) => start(addr, emulator).await,
+            Err(e) => println!("Can't start enigo: {}", e),
+        },
+        #[cfg(target_os = "linux")]
+        Some("--uinput") => match UinputEmulator::new() {
+            Ok(emulator) => start(addr, emulator).await,
+            Err(e) => println!("Can't create uinput devices: {}", e),
+        },
+        Some("--boot") => start(addr, boot_interfaces(ReportMode::Boot)).await,
+        Some("--nkro") => start(addr, boot_interfaces(ReportMode::Nkro)).await,
+        Some("--fallback") => {
+            let mut backends: Vec<Box<dyn Emulator + Send + Sync>> =
+                vec![Box::new(HidEmulator::new(0xa56, 0xa56, 1))];
+            match EnigoEmulator::new() {
+                Ok(enigo) => backends.push(Box::new(enigo)),
+                Err(e) => println!("Can't start enigo, running without a fallback: {}", e),
+            }
+            start(addr, CompositeEmulator::new(backends)).await
+        }
+        _ => start(addr, HidEmulator::new(0xa56, 0xa56, 1)).await,
+    }
+}
+
+/// Boot devices put the keyboard and the mouse on interfaces of their own. Each only
+/// takes the reports its descriptor declares, so everything goes to both
+fn boot_interfaces(mode: ReportMode) -> CompositeEmulator {
+    CompositeEmulator::new(
+        [0, 1]
+            .into_iter()
+            .map(|i_num| {
+                Box::new(HidEmulator::with_mode(0xa56, 0xa56, i_num, mode))
+                    as Box<dyn Emulator + Send + Sync>
+            })
+            .collect(),
+    )
+}
+
+async fn start<E: Emulator + Send + Sync + 'static>(addr: &str, emulator: E) {
+    let emulator = with_gamepads(emulator);
+    if std::env::args().any(|arg| arg == "--slow") {
+        serve(addr, PacedEmulator::new(emulator, Pacing::slow())).await
+    } else {
+        serve(addr, emulator).await
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn with_gamepads<E: Emulator>(emulator: E) -> UinputGamepads<E> {
+    UinputGamepads::new(emulator)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn with_gamepads<E: Emulator>(emulator: E) -> E {
+    emulator
+}
+
+async fn serve<E: Emulator + Send + Sync + 'static>(addr: &str, emulator: E) {
+    let emulator = Arc::new(emulator);
+    // Shared by every connection, so one that dies without cleaning up is corrected by the
+    // next one's snapshot
+    let held = Arc::new(std::sync::Mutex::new(InputState::new()));
     loop {
         let listener = TcpListener::bind(addr).await;
         let listener = if listener.is_ok() {
@@ -28,10 +102,20 @@ async fn main() {
         stream.set_nodelay(true).unwrap();
         let (wifi_rx, wifi_tx) = stream.into_split();
         let (display_tx, display_rx) = mpsc::channel::<()>(10);
-        let inputs = Inputs::new(wifi_rx, emulator.clone(), display_tx);
-        let audio = Audio::new(wifi_tx).unwrap();
+        let (data_tx, data_rx) = mpsc::channel(20);
+        let inputs = Inputs::new(wifi_rx, emulator.clone(), display_tx, held.clone());
+        let audio = Audio::new(data_tx.clone()).unwrap();
+        let status = StatusForwarder::new(emulator.status(), data_tx.clone());
+        let locks = emulator
+            .locks()
+            .map(|locks_rx| LockForwarder::new(locks_rx, data_tx));
+        let shared_sender = SharedSender::new(wifi_tx, data_rx);
         let inputs_handle = tokio::spawn(inputs.handle_loop());
         let audio_handle = tokio::spawn(audio.handle_loop());
+        // Finishes early for emulators without a status, so it isn't part of the select
+        let status_handle = tokio::spawn(status.handle_loop());
+        let locks_handle = locks.map(|locks| tokio::spawn(locks.handle_loop()));
+        let sender_handle = tokio::spawn(shared_sender.write_loop());
         let display_handle = tokio::task::spawn_blocking(move || {
             let display_control = DisplayControl::new("G274QPF E2", display_rx);
             display_control.handle_loop();
@@ -39,14 +123,21 @@ async fn main() {
         let audio_handle_ab = audio_handle.abort_handle();
         let inputs_handle_ab = inputs_handle.abort_handle();
         let display_handle_ab = display_handle.abort_handle();
+        let sender_handle_ab = sender_handle.abort_handle();
         select! {
             _ = inputs_handle => {
             },
             _ = audio_handle => {},
             _ = display_handle => {},
+            _ = sender_handle => {},
         };
         audio_handle_ab.abort();
         inputs_handle_ab.abort();
         display_handle_ab.abort();
+        sender_handle_ab.abort();
+        status_handle.abort();
+        if let Some(locks_handle) = locks_handle {
+            locks_handle.abort();
+        }
     }
 }
@@ -1,7 +1,6 @@
 use std::sync::Arc;
 
 use anyhow::Result;
-use bytemuck::cast_slice;
 use cpal::{
     BufferSize, Stream, StreamConfig,
     traits::{DeviceTrait, HostTrait, StreamTrait},
@@ -12,59 +11,115 @@ use ringbuf::{
     traits::{Consumer, Producer, Split},
 };
 use shared::{
-    codes::{ChannelData, HidEvent},
-    emulator::{Emulator, WinputEmulator},
+    codes::{ChannelData, EmulatorStatus, HidEvent, LockState, ServerData},
+    emulator::{Emulator, EmulatorError, WinputEmulator},
+    state::InputState,
 };
 use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt},
+    io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
     net::tcp::{OwnedReadHalf, OwnedWriteHalf},
     sync::{
         Mutex,
         mpsc::{self, Receiver, Sender},
+        watch,
     },
 };
 
-pub struct Inputs<E: Emulator> {
-    wifi_rx: OwnedReadHalf,
+/// Reads length prefixed `ChannelData` off `wifi_rx`, which is the client's TCP stream
+/// outside of tests
+pub struct Inputs<E: Emulator, R = OwnedReadHalf> {
+    wifi_rx: R,
     emulator: Arc<E>,
     display_tx: Sender<()>,
+    // What the emulator was told is held, by this connection and the ones before it, to
+    // compare snapshots against
+    held: Arc<std::sync::Mutex<InputState>>,
 }
 
-impl<E: Emulator> Inputs<E> {
-    pub fn new(wifi_rx: OwnedReadHalf, emulator: Arc<E>, display_tx: Sender<()>) -> Self {
+impl<E: Emulator, R: AsyncRead + Unpin> Inputs<E, R> {
+    /// `held` outlives the connection, so a client that reconnects gets back whatever
+    /// its last connection left pressed
+    pub fn new(
+        wifi_rx: R,
+        emulator: Arc<E>,
+        display_tx: Sender<()>,
+        held: Arc<std::sync::Mutex<InputState>>,
+    ) -> Self {
         Self {
             wifi_rx,
             emulator,
             display_tx,
+            held,
         }
     }
 
+    fn emulate_input(&mut self, hid_event: &HidEvent) -> Result<(), EmulatorError> {
+        self.held.lock().unwrap().apply(hid_event);
+        self.emulator.emulate_input(hid_event)
+    }
+
+    /// Presses and releases whatever differs from the client's snapshot
+    fn reconcile(&mut self, snapshot: &InputState) -> Result<(), EmulatorError> {
+        let fixes = self.held.lock().unwrap().diff(snapshot);
+        if !fixes.is_empty() {
+            println!("Correcting {} held keys and buttons", fixes.len());
+        }
+        // Keep going after a failure, a backend that queues still wants the rest
+        let mut res = Ok(());
+        for hid_event in fixes {
+            if let Err(e) = self.emulate_input(&hid_event) {
+                res = Err(e);
+            }
+        }
+        res
+    }
+
+    /// Runs until the connection ends, then lets go of everything the client held
     pub async fn handle_loop(mut self) -> Result<()> {
-        let mut buf = vec![0u8; 32];
+        let res = self.read_loop().await;
+        report(self.reconcile(&InputState::new()));
+        res
+    }
+
+    async fn read_loop(&mut self) -> Result<()> {
+        let mut buf = vec![0u8; u8::MAX as usize];
         loop {
             let size = self.wifi_rx.read_u8().await? as usize;
             self.wifi_rx.read_exact(&mut buf[..size]).await?;
             let event = bincode::deserialize::<ChannelData>(&buf[..size])?;
-            match event {
-                ChannelData::Hid(hid_event) => {
-                    self.emulator.emulate_input(&hid_event);
+            let res = match event {
+                ChannelData::Hid(hid_event) | ChannelData::Unmapped(hid_event) => {
+                    self.emulate_input(&hid_event)
                 }
                 ChannelData::ChangeDisplay => {
                     self.display_tx.send(()).await?;
+                    Ok(())
                 }
-            }
+                ChannelData::Text(text) => self.emulator.emulate_text(&text),
+                ChannelData::Snapshot(snapshot) => self.reconcile(&snapshot),
+            };
+            report(res);
         }
     }
 }
 
+// A missing device already shows up in the status sent to the client
+fn report(res: Result<(), EmulatorError>) {
+    match res {
+        Err(EmulatorError::Failed(e)) => println!("{}", e),
+        Err(e @ EmulatorError::Unsupported(_)) => println!("{}", e),
+        _ => (),
+    }
+}
+
 pub struct Audio {
-    wifi_tx: OwnedWriteHalf,
+    data_tx: Sender<ServerData>,
     audio_rx: Receiver<Vec<f32>>,
     stream: Stream,
 }
 
 impl Audio {
-    pub fn new(wifi_tx: OwnedWriteHalf) -> Result<Self> {
+    pub fn new(data_tx: Sender<ServerData>) -> Result<Self> {
         let host = cpal::default_host();
 
         let device = host
@@ -105,7 +160,7 @@ impl Audio {
         let input_stream = device.build_input_stream(&config, input_data, err_fn, None)?;
         input_stream.play()?;
         Ok(Self {
-            wifi_tx,
+            data_tx,
             audio_rx,
             stream: input_stream,
         })
@@ -118,10 +173,76 @@ impl Audio {
                 .recv()
                 .await
                 .ok_or(anyhow::Error::msg("channel closed"))?;
-            self.wifi_tx.write_all(cast_slice(&buf)).await?;
+            self.data_tx.send(ServerData::Audio(buf)).await?;
+        }
+    }
+}
+
+/// Sends the emulator's status to the client, starting with the current one
+pub struct StatusForwarder {
+    status_rx: watch::Receiver<EmulatorStatus>,
+    data_tx: Sender<ServerData>,
+}
+
+impl StatusForwarder {
+    pub fn new(status_rx: watch::Receiver<EmulatorStatus>, data_tx: Sender<ServerData>) -> Self {
+        Self { status_rx, data_tx }
+    }
+
+    pub async fn handle_loop(mut self) -> Result<()> {
+        loop {
+            let status = self.status_rx.borrow_and_update().clone();
+            self.data_tx.send(ServerData::Status(status)).await?;
+            // Backends that can't lose their device drop the sender right away
+            if self.status_rx.changed().await.is_err() {
+                return Ok(());
+            }
         }
     }
 }
+
+/// Sends the target's lock state to the client, starting with the current one
+pub struct LockForwarder {
+    locks_rx: watch::Receiver<LockState>,
+    data_tx: Sender<ServerData>,
+}
+
+impl LockForwarder {
+    pub fn new(locks_rx: watch::Receiver<LockState>, data_tx: Sender<ServerData>) -> Self {
+        Self { locks_rx, data_tx }
+    }
+
+    pub async fn handle_loop(mut self) -> Result<()> {
+        loop {
+            let locks = *self.locks_rx.borrow_and_update();
+            self.data_tx.send(ServerData::Locks(locks)).await?;
+            if self.locks_rx.changed().await.is_err() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Frames everything headed to the client onto the single write half
+pub struct SharedSender {
+    wifi_tx: OwnedWriteHalf,
+    rx: Receiver<ServerData>,
+}
+
+impl SharedSender {
+    pub fn new(wifi_tx: OwnedWriteHalf, rx: Receiver<ServerData>) -> Self {
+        Self { wifi_tx, rx }
+    }
+
+    pub async fn write_loop(mut self) -> Result<()> {
+        while let Some(data) = self.rx.recv().await {
+            let mesg = bincode::serialize(&data)?;
+            self.wifi_tx.write_u32_le(mesg.len() as u32).await?;
+            self.wifi_tx.write_all(&mesg).await?;
+        }
+        Ok(())
+    }
+}
 pub struct DisplayControl {
     display: Display,
     rx: Receiver<()>,
@@ -150,3 +271,155 @@ impl DisplayControl {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use shared::{codes::ScanCode, emulator::RecordingEmulator};
+    use winit::{event::ElementState, keyboard::KeyCode};
+
+    use super::*;
+
+    fn frame(data: &ChannelData) -> Vec<u8> {
+        let mesg = bincode::serialize(data).unwrap();
+        let mut frame = vec![u8::try_from(mesg.len()).unwrap()];
+        frame.extend(mesg);
+        frame
+    }
+
+    fn key(code: KeyCode, dir: ElementState) -> HidEvent {
+        HidEvent::Key(ScanCode::new(code, dir))
+    }
+
+    /// Runs `handle_loop` over `frames` until it stops, at the end of them at the latest
+    async fn run(frames: Vec<u8>) -> (Arc<RecordingEmulator>, anyhow::Error) {
+        let emulator = Arc::new(RecordingEmulator::new());
+        let (display_tx, _display_rx) = mpsc::channel(1);
+        let held = Arc::new(std::sync::Mutex::new(InputState::new()));
+        let inputs = Inputs::new(frames.as_slice(), emulator.clone(), display_tx, held);
+        let e = inputs.handle_loop().await.unwrap_err();
+        (emulator, e)
+    }
+
+    fn is_eof(e: &anyhow::Error) -> bool {
+        e.downcast_ref::<std::io::Error>()
+            .is_some_and(|e| e.kind() == std::io::ErrorKind::UnexpectedEof)
+    }
+
+    #[tokio::test]
+    async fn emulates_frames_in_order() {
+        let events = [
+            key(KeyCode::KeyA, ElementState::Pressed),
+            HidEvent::MouseDelta(-4, 7),
+            key(KeyCode::KeyA, ElementState::Released),
+            HidEvent::MouseScroll(-1),
+        ];
+        let frames = events
+            .iter()
+            .flat_map(|&hid_event| frame(&ChannelData::Hid(hid_event)))
+            .collect();
+        let (emulator, e) = run(frames).await;
+        assert!(is_eof(&e), "{}", e);
+        emulator.assert_events(&events);
+        emulator.assert_all_released();
+    }
+
+    #[tokio::test]
+    async fn stops_at_a_bad_frame() {
+        let mut frames = frame(&ChannelData::Hid(key(KeyCode::KeyB, ElementState::Pressed)));
+        // No ChannelData variant has that index
+        frames.extend([4, 0xff, 0xff, 0xff, 0xff]);
+        frames.extend(frame(&ChannelData::Hid(HidEvent::MouseDelta(1, 1))));
+        let (emulator, e) = run(frames).await;
+        assert!(e.downcast_ref::<bincode::Error>().is_some(), "{}", e);
+        // Let go of once the connection is dropped
+        emulator.assert_events(&[
+            key(KeyCode::KeyB, ElementState::Pressed),
+            key(KeyCode::KeyB, ElementState::Released),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn stops_at_a_truncated_frame() {
+        let mut frames = frame(&ChannelData::Hid(HidEvent::MouseDelta(2, 3)));
+        let mut truncated = frame(&ChannelData::Hid(HidEvent::MouseScroll(1)));
+        truncated.pop();
+        frames.extend(truncated);
+        let (emulator, e) = run(frames).await;
+        assert!(is_eof(&e), "{}", e);
+        emulator.assert_events(&[HidEvent::MouseDelta(2, 3)]);
+    }
+
+    #[tokio::test]
+    async fn takes_a_frame_at_the_length_limit() {
+        // The enum index and the string length take up 12 bytes
+        let text = "x".repeat(u8::MAX as usize - 12);
+        let data = ChannelData::Text(text.clone());
+        let mut frames = frame(&data);
+        assert_eq!(frames[0], u8::MAX);
+        frames.extend(frame(&ChannelData::Hid(HidEvent::MouseScroll(2))));
+        let (emulator, e) = run(frames).await;
+        assert!(is_eof(&e), "{}", e);
+        assert_eq!(emulator.text(), text);
+        emulator.assert_events(&[HidEvent::MouseScroll(2)]);
+    }
+
+    #[tokio::test]
+    async fn snapshot_releases_what_the_client_let_go() {
+        let mut frames = Vec::new();
+        for code in [KeyCode::ShiftLeft, KeyCode::KeyC] {
+            frames.extend(frame(&ChannelData::Hid(key(code, ElementState::Pressed))));
+        }
+        let mut snapshot = InputState::new();
+        snapshot.apply(&key(KeyCode::ShiftLeft, ElementState::Pressed));
+        frames.extend(frame(&ChannelData::Snapshot(snapshot)));
+        let (emulator, e) = run(frames).await;
+        assert!(is_eof(&e), "{}", e);
+        emulator.assert_contains_sequence(&[
+            key(KeyCode::KeyC, ElementState::Pressed),
+            key(KeyCode::KeyC, ElementState::Released),
+            key(KeyCode::ShiftLeft, ElementState::Released),
+        ]);
+        emulator.assert_all_released();
+    }
+
+    #[tokio::test]
+    async fn reconnect_snapshot_releases_what_a_dropped_connection_held() {
+        let emulator = Arc::new(RecordingEmulator::new());
+        let held = Arc::new(std::sync::Mutex::new(InputState::new()));
+        let (display_tx, _display_rx) = mpsc::channel(1);
+
+        // The first connection is torn down before it can clean up after itself
+        let (mut client, server) = tokio::io::duplex(64);
+        let inputs = Inputs::new(server, emulator.clone(), display_tx.clone(), held.clone());
+        let task = tokio::spawn(inputs.handle_loop());
+        let press = key(KeyCode::KeyA, ElementState::Pressed);
+        client
+            .write_all(&frame(&ChannelData::Hid(press)))
+            .await
+            .unwrap();
+        assert!(emulator.wait_for_events(1, Duration::from_secs(1)).await);
+        task.abort();
+        let _ = task.await;
+        emulator.assert_keys_held(&[KeyCode::KeyA]);
+
+        let frames = frame(&ChannelData::Snapshot(InputState::new()));
+        let inputs = Inputs::new(frames.as_slice(), emulator.clone(), display_tx, held);
+        assert!(inputs.handle_loop().await.is_err());
+        emulator.assert_events(&[press, key(KeyCode::KeyA, ElementState::Released)]);
+    }
+
+    #[tokio::test]
+    async fn forwards_display_changes() {
+        let emulator = Arc::new(RecordingEmulator::new());
+        let (display_tx, mut display_rx) = mpsc::channel(1);
+        let frames = frame(&ChannelData::ChangeDisplay);
+        let held = Arc::new(std::sync::Mutex::new(InputState::new()));
+        let inputs = Inputs::new(frames.as_slice(), emulator.clone(), display_tx, held);
+        assert!(inputs.handle_loop().await.is_err());
+        let changed = tokio::time::timeout(Duration::from_secs(1), display_rx.recv()).await;
+        assert_eq!(changed.unwrap(), Some(()));
+        emulator.assert_events(&[]);
+    }
+}
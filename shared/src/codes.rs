@@ -1,15 +1,87 @@
+use enigo::Key;
 use serde::{Deserialize, Serialize};
 use winit::event::MouseButton;
 use winit::{event::ElementState, keyboard::KeyCode};
 #[cfg(target_os = "windows")]
 use winput::Vk;
 
+#[cfg(target_os = "linux")]
+use evdev::KeyCode as EvdevKey;
+
 use crate::scan_codes::HidCodes;
+use crate::state::InputState;
+
+/// Longest text in bytes carried by a single [`ChannelData::Text`] so the serialized
+/// message still fits behind its one byte length prefix
+pub const MAX_TEXT_LEN: usize = 200;
 
 #[derive(Serialize, Deserialize)]
 pub enum ChannelData {
     Hid(HidEvent),
     ChangeDisplay,
+    /// Text typed on the server as-is, independent of either side's keyboard layout
+    Text(String),
+    /// Everything the client holds down. The server presses and releases whatever it
+    /// needs to match, in case an event was lost along the way
+    Snapshot(InputState),
+    /// Sent as-is, past the client's dual-role keys, layers and remap table, e.g. the
+    /// taps that line the target's locks up with the client's
+    Unmapped(HidEvent),
+}
+
+/// Health of the server's output device, forwarded to the client whenever it changes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EmulatorStatus {
+    Connected,
+    /// The device went away and the server is waiting for it to come back
+    Searching,
+    Error(String),
+}
+
+/// Everything the server sends to the client. Framed as `[len u32 le][bincode]`
+#[derive(Serialize, Deserialize)]
+pub enum ServerData {
+    /// Interleaved stereo samples
+    Audio(Vec<f32>),
+    Status(EmulatorStatus),
+    Locks(LockState),
+}
+
+/// Lock keys that are on at the target, as its keyboard LEDs show them
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockState {
+    pub caps: bool,
+    pub num: bool,
+    pub scroll: bool,
+}
+
+impl LockState {
+    /// From the LED bits of a keyboard output report, Num Lock being bit 0
+    pub fn from_hid_leds(leds: u8) -> Self {
+        Self {
+            num: leds & 0x01 != 0,
+            caps: leds & 0x02 != 0,
+            scroll: leds & 0x04 != 0,
+        }
+    }
+}
+
+impl ChannelData {
+    /// Splits `text` into as many `Text` messages as needed to respect [`MAX_TEXT_LEN`]
+    pub fn text(text: &str) -> Vec<ChannelData> {
+        let mut chunks = Vec::new();
+        let mut start = 0;
+        for (i, c) in text.char_indices() {
+            if i + c.len_utf8() - start > MAX_TEXT_LEN {
+                chunks.push(ChannelData::Text(text[start..i].to_string()));
+                start = i;
+            }
+        }
+        if start < text.len() {
+            chunks.push(ChannelData::Text(text[start..].to_string()));
+        }
+        chunks
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -18,6 +90,15 @@ pub enum HidEvent {
     MouseDelta(i32, i32),
     MouseButton(MouseButtons),
     MouseScroll(i32),
+    /// Pointer position normalized to the target's screen, 0 being the left/top edge and
+    /// `u16::MAX` the right/bottom edge
+    MouseAbsolute {
+        x: u16,
+        y: u16,
+    },
+    Touch(TouchContact),
+    Pen(PenState),
+    Gamepad(GamepadState),
 }
 
 impl HidEvent {
@@ -48,6 +129,107 @@ impl HidEvent {
     }
 }
 
+/// One finger on a touch surface. Positions are normalized to the capture window with
+/// 0 being the left/top edge and `u16::MAX` the right/bottom edge
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TouchContact {
+    /// Contact slot, stable for as long as the finger stays down
+    pub id: u8,
+    /// False once the finger is lifted, which ends the contact
+    pub touching: bool,
+    pub x: u16,
+    pub y: u16,
+}
+
+/// Full state of a pen or stylus, positioned the same way as [`TouchContact`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PenState {
+    pub in_range: bool,
+    pub tip: bool,
+    pub x: u16,
+    pub y: u16,
+    /// Tip pressure, 0 to `u16::MAX`
+    pub pressure: u16,
+    /// Tilt from the vertical in degrees, -90 to 90 on each axis
+    pub tilt_x: i8,
+    pub tilt_y: i8,
+}
+
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    South = 0,
+    East = 1,
+    North = 2,
+    West = 3,
+    LeftBumper = 4,
+    RightBumper = 5,
+    Select = 6,
+    Start = 7,
+    Mode = 8,
+    LeftThumb = 9,
+    RightThumb = 10,
+    DPadUp = 11,
+    DPadDown = 12,
+    DPadLeft = 13,
+    DPadRight = 14,
+}
+
+impl GamepadButton {
+    pub const ALL: [GamepadButton; 15] = [
+        GamepadButton::South,
+        GamepadButton::East,
+        GamepadButton::North,
+        GamepadButton::West,
+        GamepadButton::LeftBumper,
+        GamepadButton::RightBumper,
+        GamepadButton::Select,
+        GamepadButton::Start,
+        GamepadButton::Mode,
+        GamepadButton::LeftThumb,
+        GamepadButton::RightThumb,
+        GamepadButton::DPadUp,
+        GamepadButton::DPadDown,
+        GamepadButton::DPadLeft,
+        GamepadButton::DPadRight,
+    ];
+}
+
+/// Complete state of one game controller. Controllers are always sent as a full snapshot
+/// so a lost update can't leave a button held or a stick deflected
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct GamepadState {
+    pub id: u8,
+    /// One bit per [`GamepadButton`]
+    pub buttons: u32,
+    /// Stick positions from -32767 to 32767 with positive y pointing up
+    pub left_stick: (i16, i16),
+    pub right_stick: (i16, i16),
+    pub left_trigger: u8,
+    pub right_trigger: u8,
+}
+
+impl GamepadState {
+    pub fn new(id: u8) -> Self {
+        Self {
+            id,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_pressed(&self, button: GamepadButton) -> bool {
+        self.buttons & (1 << button as u8) != 0
+    }
+
+    pub fn set_button(&mut self, button: GamepadButton, pressed: bool) {
+        if pressed {
+            self.buttons |= 1 << button as u8;
+        } else {
+            self.buttons &= !(1 << button as u8);
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ScanCode {
     code: KeyCode,
@@ -59,6 +241,37 @@ impl ScanCode {
         Self { code, dir }
     }
 
+    pub fn code(&self) -> KeyCode {
+        self.code
+    }
+
+    /// Usage on the consumer page (0x0C) for media, volume and launcher keys. These take
+    /// precedence over [`ScanCode::to_hid`], whose volume keys most hosts ignore
+    pub fn to_consumer(&self) -> Option<u16> {
+        match self.code {
+            KeyCode::MediaTrackNext => Some(0xB5),
+            KeyCode::MediaTrackPrevious => Some(0xB6),
+            KeyCode::MediaStop => Some(0xB7),
+            KeyCode::Eject => Some(0xB8),
+            KeyCode::MediaPlayPause => Some(0xCD),
+            KeyCode::AudioVolumeMute => Some(0xE2),
+            KeyCode::AudioVolumeUp => Some(0xE9),
+            KeyCode::AudioVolumeDown => Some(0xEA),
+            KeyCode::MediaSelect => Some(0x183),
+            KeyCode::LaunchMail => Some(0x18A),
+            KeyCode::LaunchApp2 => Some(0x192),
+            KeyCode::LaunchApp1 => Some(0x194),
+            KeyCode::BrowserSearch => Some(0x221),
+            KeyCode::BrowserHome => Some(0x223),
+            KeyCode::BrowserBack => Some(0x224),
+            KeyCode::BrowserForward => Some(0x225),
+            KeyCode::BrowserStop => Some(0x226),
+            KeyCode::BrowserRefresh => Some(0x227),
+            KeyCode::BrowserFavorites => Some(0x22A),
+            _ => None,
+        }
+    }
+
     pub fn to_hid(&self) -> Option<HidCodes> {
         match self.code {
             KeyCode::Backquote => Some(HidCodes::KeyboardBacktickTilde),
@@ -109,14 +322,14 @@ impl ScanCode {
             KeyCode::Semicolon => Some(HidCodes::KeyboardSemiColon),
             KeyCode::Slash => Some(HidCodes::KeyboardSlashQuestion),
             KeyCode::AltLeft => Some(HidCodes::KeyboardLeftAlt),
-            KeyCode::AltRight => Some(HidCodes::KeyboardLeftGUI),
+            KeyCode::AltRight => Some(HidCodes::KeyboardRightAlt),
             KeyCode::Backspace => Some(HidCodes::KeyboardBackspace),
             KeyCode::CapsLock => Some(HidCodes::KeyboardCapsLock),
             KeyCode::ControlLeft => Some(HidCodes::KeyboardLeftControl),
             KeyCode::ControlRight => Some(HidCodes::KeyboardRightControl),
             KeyCode::Enter => Some(HidCodes::KeyboardEnter),
-            KeyCode::SuperLeft => None,
-            KeyCode::SuperRight => None,
+            KeyCode::SuperLeft => Some(HidCodes::KeyboardLeftGUI),
+            KeyCode::SuperRight => Some(HidCodes::KeyboardRightGUI),
             KeyCode::ShiftLeft => Some(HidCodes::KeyboardLeftShift),
             KeyCode::ShiftRight => Some(HidCodes::KeyboardRightShift),
             KeyCode::Space => Some(HidCodes::KeyboardSpacebar),
@@ -142,26 +355,27 @@ impl ScanCode {
             KeyCode::Numpad7 => Some(HidCodes::Keypad7Home),
             KeyCode::Numpad8 => Some(HidCodes::Keypad8UpArrow),
             KeyCode::Numpad9 => Some(HidCodes::Keypad9PageUp),
-            KeyCode::NumpadAdd => Some(HidCodes::KeypadMemoryAdd),
+            KeyCode::NumpadAdd => Some(HidCodes::KeypadPlus),
             KeyCode::NumpadBackspace => Some(HidCodes::KeypadBackspace),
             KeyCode::NumpadClear => Some(HidCodes::KeypadClear),
             KeyCode::NumpadClearEntry => Some(HidCodes::KeypadClearEntry),
             KeyCode::NumpadComma => Some(HidCodes::KeypadComma),
-            KeyCode::NumpadDecimal => todo!(),
-            KeyCode::NumpadDivide => todo!(),
-            KeyCode::NumpadEnter => todo!(),
-            KeyCode::NumpadEqual => todo!(),
-            KeyCode::NumpadHash => todo!(),
-            KeyCode::NumpadMemoryAdd => todo!(),
-            KeyCode::NumpadMemoryClear => todo!(),
-            KeyCode::NumpadMemoryRecall => todo!(),
-            KeyCode::NumpadMemoryStore => todo!(),
-            KeyCode::NumpadMemorySubtract => todo!(),
-            KeyCode::NumpadMultiply => todo!(),
-            KeyCode::NumpadParenLeft => todo!(),
-            KeyCode::NumpadParenRight => todo!(),
-            KeyCode::NumpadStar => todo!(),
-            KeyCode::NumpadSubtract => todo!(),
+            KeyCode::NumpadDecimal => Some(HidCodes::KeypadPeriodDelete),
+            KeyCode::NumpadDivide => Some(HidCodes::KeypadDivide),
+            KeyCode::NumpadEnter => Some(HidCodes::KeypadEnter),
+            KeyCode::NumpadEqual => Some(HidCodes::KeypadEqual),
+            KeyCode::NumpadHash => Some(HidCodes::KeypadHash),
+            KeyCode::NumpadMemoryAdd => Some(HidCodes::KeypadMemoryAdd),
+            KeyCode::NumpadMemoryClear => Some(HidCodes::KeypadMemoryClear),
+            KeyCode::NumpadMemoryRecall => Some(HidCodes::KeypadMemoryRecall),
+            KeyCode::NumpadMemoryStore => Some(HidCodes::KeypadMemoryStore),
+            KeyCode::NumpadMemorySubtract => Some(HidCodes::KeypadMemorySubtract),
+            KeyCode::NumpadMultiply => Some(HidCodes::KeypadMultiply),
+            KeyCode::NumpadParenLeft => Some(HidCodes::KeypadOpenParens),
+            KeyCode::NumpadParenRight => Some(HidCodes::KeypadCloseParens),
+            // The star of a phone keypad, closest to the keypad's multiply
+            KeyCode::NumpadStar => Some(HidCodes::KeypadMultiply),
+            KeyCode::NumpadSubtract => Some(HidCodes::KeypadMinus),
             KeyCode::Escape => Some(HidCodes::KeyboardEscape),
             KeyCode::PrintScreen => Some(HidCodes::KeyboardPrintScreen),
             KeyCode::ScrollLock => Some(HidCodes::KeyboardScrollLock),
@@ -169,6 +383,30 @@ impl ScanCode {
             KeyCode::AudioVolumeDown => Some(HidCodes::KeyboardVolumeDown),
             KeyCode::AudioVolumeMute => Some(HidCodes::KeyboardMute),
             KeyCode::AudioVolumeUp => Some(HidCodes::KeyboardVolumeUp),
+            KeyCode::IntlBackslash => Some(HidCodes::KeyboardNonUSSlash),
+            KeyCode::IntlRo => Some(HidCodes::KeyboardInternational1),
+            KeyCode::KanaMode => Some(HidCodes::KeyboardInternational2),
+            KeyCode::IntlYen => Some(HidCodes::KeyboardInternational3),
+            KeyCode::Convert => Some(HidCodes::KeyboardInternational4),
+            KeyCode::NonConvert => Some(HidCodes::KeyboardInternational5),
+            KeyCode::Lang1 => Some(HidCodes::KeyboardLANG1),
+            KeyCode::Lang2 => Some(HidCodes::KeyboardLANG2),
+            KeyCode::Lang3 | KeyCode::Katakana => Some(HidCodes::KeyboardLANG3),
+            KeyCode::Lang4 | KeyCode::Hiragana => Some(HidCodes::KeyboardLANG4),
+            KeyCode::Lang5 => Some(HidCodes::KeyboardLANG5),
+            KeyCode::ContextMenu => Some(HidCodes::KeyboardApplication),
+            KeyCode::Power => Some(HidCodes::KeyboardPower),
+            KeyCode::Help => Some(HidCodes::KeyboardHelp),
+            KeyCode::Open => Some(HidCodes::KeyboardExecute),
+            KeyCode::Select => Some(HidCodes::KeyboardSelect),
+            KeyCode::Again => Some(HidCodes::KeyboardAgain),
+            KeyCode::Undo => Some(HidCodes::KeyboardUndo),
+            KeyCode::Cut => Some(HidCodes::KeyboardCut),
+            KeyCode::Copy => Some(HidCodes::KeyboardCopy),
+            KeyCode::Paste => Some(HidCodes::KeyboardPaste),
+            KeyCode::Find => Some(HidCodes::KeyboardFind),
+            KeyCode::Abort => Some(HidCodes::KeyboardCancel),
+            KeyCode::Props => Some(HidCodes::KeyboardCrSelProps),
             KeyCode::F1 => Some(HidCodes::KeyboardF1),
             KeyCode::F2 => Some(HidCodes::KeyboardF2),
             KeyCode::F3 => Some(HidCodes::KeyboardF3),
@@ -181,30 +419,21 @@ impl ScanCode {
             KeyCode::F10 => Some(HidCodes::KeyboardF10),
             KeyCode::F11 => Some(HidCodes::KeyboardF11),
             KeyCode::F12 => Some(HidCodes::KeyboardF12),
-            KeyCode::F13 => todo!(),
-            KeyCode::F14 => todo!(),
-            KeyCode::F15 => todo!(),
-            KeyCode::F16 => todo!(),
-            KeyCode::F17 => todo!(),
-            KeyCode::F18 => todo!(),
-            KeyCode::F19 => todo!(),
-            KeyCode::F20 => todo!(),
-            KeyCode::F21 => todo!(),
-            KeyCode::F22 => todo!(),
-            KeyCode::F23 => todo!(),
-            KeyCode::F24 => todo!(),
-            KeyCode::F25 => todo!(),
-            KeyCode::F26 => todo!(),
-            KeyCode::F27 => todo!(),
-            KeyCode::F28 => todo!(),
-            KeyCode::F29 => todo!(),
-            KeyCode::F30 => todo!(),
-            KeyCode::F31 => todo!(),
-            KeyCode::F32 => todo!(),
-            KeyCode::F33 => todo!(),
-            KeyCode::F34 => todo!(),
-            KeyCode::F35 => todo!(),
-            _ => panic!("Shouldn't be here"),
+            KeyCode::F13 => Some(HidCodes::KeyboardF13),
+            KeyCode::F14 => Some(HidCodes::KeyboardF14),
+            KeyCode::F15 => Some(HidCodes::KeyboardF15),
+            KeyCode::F16 => Some(HidCodes::KeyboardF16),
+            KeyCode::F17 => Some(HidCodes::KeyboardF17),
+            KeyCode::F18 => Some(HidCodes::KeyboardF18),
+            KeyCode::F19 => Some(HidCodes::KeyboardF19),
+            KeyCode::F20 => Some(HidCodes::KeyboardF20),
+            KeyCode::F21 => Some(HidCodes::KeyboardF21),
+            KeyCode::F22 => Some(HidCodes::KeyboardF22),
+            KeyCode::F23 => Some(HidCodes::KeyboardF23),
+            KeyCode::F24 => Some(HidCodes::KeyboardF24),
+            // F25 and up have no keyboard usage. Fn never reaches the host, and media,
+            // browser and power management keys live on other pages
+            _ => None,
         }
     }
 
@@ -316,8 +545,8 @@ impl ScanCode {
             KeyCode::AudioVolumeDown => Some(Vk::VolumeDown),
             KeyCode::AudioVolumeMute => Some(Vk::VolumeMute),
             KeyCode::AudioVolumeUp => Some(Vk::VolumeUp),
-            KeyCode::SuperLeft => None,
-            KeyCode::SuperRight => None,
+            KeyCode::SuperLeft => Some(Vk::LeftWin),
+            KeyCode::SuperRight => Some(Vk::RightWin),
             KeyCode::ContextMenu => Some(Vk::Apps),
             KeyCode::BrowserBack => Some(Vk::BrowserBack),
             KeyCode::BrowserFavorites => Some(Vk::BrowserFavorites),
@@ -348,9 +577,597 @@ impl ScanCode {
             KeyCode::Slash => Some(Vk::Oem2),
             KeyCode::ArrowLeft => Some(Vk::LeftArrow),
             KeyCode::NumLock => Some(Vk::Numlock),
-            _ => todo!(),
+            _ => None,
         }
     }
+
+    /// Characters are sent as unicode keys, so the target's layout decides which physical
+    /// key they land on
+    pub fn to_enigo(&self) -> Option<Key> {
+        match self.code {
+            KeyCode::SuperLeft | KeyCode::SuperRight => Some(Key::Meta),
+            KeyCode::Digit0 => Some(Key::Unicode('0')),
+            KeyCode::Digit1 => Some(Key::Unicode('1')),
+            KeyCode::Digit2 => Some(Key::Unicode('2')),
+            KeyCode::Digit3 => Some(Key::Unicode('3')),
+            KeyCode::Digit4 => Some(Key::Unicode('4')),
+            KeyCode::Digit5 => Some(Key::Unicode('5')),
+            KeyCode::Digit6 => Some(Key::Unicode('6')),
+            KeyCode::Digit7 => Some(Key::Unicode('7')),
+            KeyCode::Digit8 => Some(Key::Unicode('8')),
+            KeyCode::Digit9 => Some(Key::Unicode('9')),
+            KeyCode::KeyA => Some(Key::Unicode('a')),
+            KeyCode::KeyB => Some(Key::Unicode('b')),
+            KeyCode::KeyC => Some(Key::Unicode('c')),
+            KeyCode::KeyD => Some(Key::Unicode('d')),
+            KeyCode::KeyE => Some(Key::Unicode('e')),
+            KeyCode::KeyF => Some(Key::Unicode('f')),
+            KeyCode::KeyG => Some(Key::Unicode('g')),
+            KeyCode::KeyH => Some(Key::Unicode('h')),
+            KeyCode::KeyI => Some(Key::Unicode('i')),
+            KeyCode::KeyJ => Some(Key::Unicode('j')),
+            KeyCode::KeyK => Some(Key::Unicode('k')),
+            KeyCode::KeyL => Some(Key::Unicode('l')),
+            KeyCode::KeyM => Some(Key::Unicode('m')),
+            KeyCode::KeyN => Some(Key::Unicode('n')),
+            KeyCode::KeyO => Some(Key::Unicode('o')),
+            KeyCode::KeyP => Some(Key::Unicode('p')),
+            KeyCode::KeyQ => Some(Key::Unicode('q')),
+            KeyCode::KeyR => Some(Key::Unicode('r')),
+            KeyCode::KeyS => Some(Key::Unicode('s')),
+            KeyCode::KeyT => Some(Key::Unicode('t')),
+            KeyCode::KeyU => Some(Key::Unicode('u')),
+            KeyCode::KeyV => Some(Key::Unicode('v')),
+            KeyCode::KeyW => Some(Key::Unicode('w')),
+            KeyCode::KeyX => Some(Key::Unicode('x')),
+            KeyCode::KeyY => Some(Key::Unicode('y')),
+            KeyCode::KeyZ => Some(Key::Unicode('z')),
+            KeyCode::Backquote => Some(Key::Unicode('`')),
+            KeyCode::Backslash => Some(Key::Unicode('\\')),
+            KeyCode::BracketLeft => Some(Key::Unicode('[')),
+            KeyCode::BracketRight => Some(Key::Unicode(']')),
+            KeyCode::Comma => Some(Key::Unicode(',')),
+            KeyCode::Equal => Some(Key::Unicode('=')),
+            KeyCode::Minus => Some(Key::Unicode('-')),
+            KeyCode::Period => Some(Key::Unicode('.')),
+            KeyCode::Quote => Some(Key::Unicode('\'')),
+            KeyCode::Semicolon => Some(Key::Unicode(';')),
+            KeyCode::Slash => Some(Key::Unicode('/')),
+            KeyCode::AltLeft => Some(Key::Alt),
+            KeyCode::AltRight => Some(Key::Meta),
+            KeyCode::Backspace => Some(Key::Backspace),
+            KeyCode::CapsLock => Some(Key::CapsLock),
+            KeyCode::ControlLeft => Some(Key::LControl),
+            KeyCode::ControlRight => Some(Key::RControl),
+            KeyCode::Enter => Some(Key::Return),
+            KeyCode::ShiftLeft => Some(Key::LShift),
+            KeyCode::ShiftRight => Some(Key::RShift),
+            KeyCode::Space => Some(Key::Space),
+            KeyCode::Tab => Some(Key::Tab),
+            KeyCode::Delete => Some(Key::Delete),
+            KeyCode::End => Some(Key::End),
+            KeyCode::Help => Some(Key::Help),
+            KeyCode::Home => Some(Key::Home),
+            KeyCode::PageDown => Some(Key::PageDown),
+            KeyCode::PageUp => Some(Key::PageUp),
+            KeyCode::ArrowDown => Some(Key::DownArrow),
+            KeyCode::ArrowLeft => Some(Key::LeftArrow),
+            KeyCode::ArrowRight => Some(Key::RightArrow),
+            KeyCode::ArrowUp => Some(Key::UpArrow),
+            KeyCode::Numpad0 => Some(Key::Numpad0),
+            KeyCode::Numpad1 => Some(Key::Numpad1),
+            KeyCode::Numpad2 => Some(Key::Numpad2),
+            KeyCode::Numpad3 => Some(Key::Numpad3),
+            KeyCode::Numpad4 => Some(Key::Numpad4),
+            KeyCode::Numpad5 => Some(Key::Numpad5),
+            KeyCode::Numpad6 => Some(Key::Numpad6),
+            KeyCode::Numpad7 => Some(Key::Numpad7),
+            KeyCode::Numpad8 => Some(Key::Numpad8),
+            KeyCode::Numpad9 => Some(Key::Numpad9),
+            KeyCode::NumpadAdd => Some(Key::Add),
+            KeyCode::NumpadDecimal => Some(Key::Decimal),
+            KeyCode::NumpadDivide => Some(Key::Divide),
+            KeyCode::NumpadMultiply => Some(Key::Multiply),
+            KeyCode::NumpadSubtract => Some(Key::Subtract),
+            KeyCode::Escape => Some(Key::Escape),
+            KeyCode::MediaPlayPause => Some(Key::MediaPlayPause),
+            KeyCode::MediaTrackNext => Some(Key::MediaNextTrack),
+            KeyCode::MediaTrackPrevious => Some(Key::MediaPrevTrack),
+            KeyCode::AudioVolumeDown => Some(Key::VolumeDown),
+            KeyCode::AudioVolumeMute => Some(Key::VolumeMute),
+            KeyCode::AudioVolumeUp => Some(Key::VolumeUp),
+            KeyCode::F1 => Some(Key::F1),
+            KeyCode::F2 => Some(Key::F2),
+            KeyCode::F3 => Some(Key::F3),
+            KeyCode::F4 => Some(Key::F4),
+            KeyCode::F5 => Some(Key::F5),
+            KeyCode::F6 => Some(Key::F6),
+            KeyCode::F7 => Some(Key::F7),
+            KeyCode::F8 => Some(Key::F8),
+            KeyCode::F9 => Some(Key::F9),
+            KeyCode::F10 => Some(Key::F10),
+            KeyCode::F11 => Some(Key::F11),
+            KeyCode::F12 => Some(Key::F12),
+            KeyCode::F13 => Some(Key::F13),
+            KeyCode::F14 => Some(Key::F14),
+            KeyCode::F15 => Some(Key::F15),
+            KeyCode::F16 => Some(Key::F16),
+            KeyCode::F17 => Some(Key::F17),
+            KeyCode::F18 => Some(Key::F18),
+            KeyCode::F19 => Some(Key::F19),
+            KeyCode::F20 => Some(Key::F20),
+            #[cfg(not(target_os = "macos"))]
+            KeyCode::Insert => Some(Key::Insert),
+            #[cfg(not(target_os = "macos"))]
+            KeyCode::NumLock => Some(Key::Numlock),
+            #[cfg(not(target_os = "macos"))]
+            KeyCode::PrintScreen => Some(Key::PrintScr),
+            #[cfg(not(target_os = "macos"))]
+            KeyCode::Pause => Some(Key::Pause),
+            #[cfg(not(target_os = "macos"))]
+            KeyCode::MediaStop => Some(Key::MediaStop),
+            #[cfg(not(target_os = "macos"))]
+            KeyCode::F21 => Some(Key::F21),
+            #[cfg(not(target_os = "macos"))]
+            KeyCode::F22 => Some(Key::F22),
+            #[cfg(not(target_os = "macos"))]
+            KeyCode::F23 => Some(Key::F23),
+            #[cfg(not(target_os = "macos"))]
+            KeyCode::F24 => Some(Key::F24),
+            #[cfg(target_os = "linux")]
+            KeyCode::ScrollLock => Some(Key::ScrollLock),
+            #[cfg(target_os = "linux")]
+            KeyCode::F25 => Some(Key::F25),
+            #[cfg(target_os = "linux")]
+            KeyCode::F26 => Some(Key::F26),
+            #[cfg(target_os = "linux")]
+            KeyCode::F27 => Some(Key::F27),
+            #[cfg(target_os = "linux")]
+            KeyCode::F28 => Some(Key::F28),
+            #[cfg(target_os = "linux")]
+            KeyCode::F29 => Some(Key::F29),
+            #[cfg(target_os = "linux")]
+            KeyCode::F30 => Some(Key::F30),
+            #[cfg(target_os = "linux")]
+            KeyCode::F31 => Some(Key::F31),
+            #[cfg(target_os = "linux")]
+            KeyCode::F32 => Some(Key::F32),
+            #[cfg(target_os = "linux")]
+            KeyCode::F33 => Some(Key::F33),
+            #[cfg(target_os = "linux")]
+            KeyCode::F34 => Some(Key::F34),
+            #[cfg(target_os = "linux")]
+            KeyCode::F35 => Some(Key::F35),
+            _ => None,
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn to_evdev(&self) -> Option<EvdevKey> {
+        match self.code {
+            KeyCode::Backquote => Some(EvdevKey::KEY_GRAVE),
+            KeyCode::Backslash => Some(EvdevKey::KEY_BACKSLASH),
+            KeyCode::BracketLeft => Some(EvdevKey::KEY_LEFTBRACE),
+            KeyCode::BracketRight => Some(EvdevKey::KEY_RIGHTBRACE),
+            KeyCode::Comma => Some(EvdevKey::KEY_COMMA),
+            KeyCode::Digit0 => Some(EvdevKey::KEY_0),
+            KeyCode::Digit1 => Some(EvdevKey::KEY_1),
+            KeyCode::Digit2 => Some(EvdevKey::KEY_2),
+            KeyCode::Digit3 => Some(EvdevKey::KEY_3),
+            KeyCode::Digit4 => Some(EvdevKey::KEY_4),
+            KeyCode::Digit5 => Some(EvdevKey::KEY_5),
+            KeyCode::Digit6 => Some(EvdevKey::KEY_6),
+            KeyCode::Digit7 => Some(EvdevKey::KEY_7),
+            KeyCode::Digit8 => Some(EvdevKey::KEY_8),
+            KeyCode::Digit9 => Some(EvdevKey::KEY_9),
+            KeyCode::Equal => Some(EvdevKey::KEY_EQUAL),
+            KeyCode::IntlBackslash => Some(EvdevKey::KEY_102ND),
+            KeyCode::IntlRo => Some(EvdevKey::KEY_RO),
+            KeyCode::IntlYen => Some(EvdevKey::KEY_YEN),
+            KeyCode::KeyA => Some(EvdevKey::KEY_A),
+            KeyCode::KeyB => Some(EvdevKey::KEY_B),
+            KeyCode::KeyC => Some(EvdevKey::KEY_C),
+            KeyCode::KeyD => Some(EvdevKey::KEY_D),
+            KeyCode::KeyE => Some(EvdevKey::KEY_E),
+            KeyCode::KeyF => Some(EvdevKey::KEY_F),
+            KeyCode::KeyG => Some(EvdevKey::KEY_G),
+            KeyCode::KeyH => Some(EvdevKey::KEY_H),
+            KeyCode::KeyI => Some(EvdevKey::KEY_I),
+            KeyCode::KeyJ => Some(EvdevKey::KEY_J),
+            KeyCode::KeyK => Some(EvdevKey::KEY_K),
+            KeyCode::KeyL => Some(EvdevKey::KEY_L),
+            KeyCode::KeyM => Some(EvdevKey::KEY_M),
+            KeyCode::KeyN => Some(EvdevKey::KEY_N),
+            KeyCode::KeyO => Some(EvdevKey::KEY_O),
+            KeyCode::KeyP => Some(EvdevKey::KEY_P),
+            KeyCode::KeyQ => Some(EvdevKey::KEY_Q),
+            KeyCode::KeyR => Some(EvdevKey::KEY_R),
+            KeyCode::KeyS => Some(EvdevKey::KEY_S),
+            KeyCode::KeyT => Some(EvdevKey::KEY_T),
+            KeyCode::KeyU => Some(EvdevKey::KEY_U),
+            KeyCode::KeyV => Some(EvdevKey::KEY_V),
+            KeyCode::KeyW => Some(EvdevKey::KEY_W),
+            KeyCode::KeyX => Some(EvdevKey::KEY_X),
+            KeyCode::KeyY => Some(EvdevKey::KEY_Y),
+            KeyCode::KeyZ => Some(EvdevKey::KEY_Z),
+            KeyCode::Minus => Some(EvdevKey::KEY_MINUS),
+            KeyCode::Period => Some(EvdevKey::KEY_DOT),
+            KeyCode::Quote => Some(EvdevKey::KEY_APOSTROPHE),
+            KeyCode::Semicolon => Some(EvdevKey::KEY_SEMICOLON),
+            KeyCode::Slash => Some(EvdevKey::KEY_SLASH),
+            KeyCode::AltLeft => Some(EvdevKey::KEY_LEFTALT),
+            KeyCode::AltRight => Some(EvdevKey::KEY_LEFTMETA),
+            KeyCode::Backspace => Some(EvdevKey::KEY_BACKSPACE),
+            KeyCode::CapsLock => Some(EvdevKey::KEY_CAPSLOCK),
+            KeyCode::ContextMenu => Some(EvdevKey::KEY_COMPOSE),
+            KeyCode::ControlLeft => Some(EvdevKey::KEY_LEFTCTRL),
+            KeyCode::ControlRight => Some(EvdevKey::KEY_RIGHTCTRL),
+            KeyCode::Enter => Some(EvdevKey::KEY_ENTER),
+            KeyCode::ShiftLeft => Some(EvdevKey::KEY_LEFTSHIFT),
+            KeyCode::ShiftRight => Some(EvdevKey::KEY_RIGHTSHIFT),
+            KeyCode::Space => Some(EvdevKey::KEY_SPACE),
+            KeyCode::Tab => Some(EvdevKey::KEY_TAB),
+            KeyCode::Convert => Some(EvdevKey::KEY_HENKAN),
+            KeyCode::KanaMode => Some(EvdevKey::KEY_KATAKANAHIRAGANA),
+            KeyCode::Lang1 => Some(EvdevKey::KEY_HANGEUL),
+            KeyCode::Lang2 => Some(EvdevKey::KEY_HANJA),
+            KeyCode::Lang3 => Some(EvdevKey::KEY_KATAKANA),
+            KeyCode::Lang4 => Some(EvdevKey::KEY_HIRAGANA),
+            KeyCode::Lang5 => Some(EvdevKey::KEY_ZENKAKUHANKAKU),
+            KeyCode::NonConvert => Some(EvdevKey::KEY_MUHENKAN),
+            KeyCode::Delete => Some(EvdevKey::KEY_DELETE),
+            KeyCode::End => Some(EvdevKey::KEY_END),
+            KeyCode::Help => Some(EvdevKey::KEY_HELP),
+            KeyCode::Home => Some(EvdevKey::KEY_HOME),
+            KeyCode::Insert => Some(EvdevKey::KEY_INSERT),
+            KeyCode::PageDown => Some(EvdevKey::KEY_PAGEDOWN),
+            KeyCode::PageUp => Some(EvdevKey::KEY_PAGEUP),
+            KeyCode::ArrowDown => Some(EvdevKey::KEY_DOWN),
+            KeyCode::ArrowLeft => Some(EvdevKey::KEY_LEFT),
+            KeyCode::ArrowRight => Some(EvdevKey::KEY_RIGHT),
+            KeyCode::ArrowUp => Some(EvdevKey::KEY_UP),
+            KeyCode::NumLock => Some(EvdevKey::KEY_NUMLOCK),
+            KeyCode::Numpad0 => Some(EvdevKey::KEY_KP0),
+            KeyCode::Numpad1 => Some(EvdevKey::KEY_KP1),
+            KeyCode::Numpad2 => Some(EvdevKey::KEY_KP2),
+            KeyCode::Numpad3 => Some(EvdevKey::KEY_KP3),
+            KeyCode::Numpad4 => Some(EvdevKey::KEY_KP4),
+            KeyCode::Numpad5 => Some(EvdevKey::KEY_KP5),
+            KeyCode::Numpad6 => Some(EvdevKey::KEY_KP6),
+            KeyCode::Numpad7 => Some(EvdevKey::KEY_KP7),
+            KeyCode::Numpad8 => Some(EvdevKey::KEY_KP8),
+            KeyCode::Numpad9 => Some(EvdevKey::KEY_KP9),
+            KeyCode::NumpadAdd => Some(EvdevKey::KEY_KPPLUS),
+            KeyCode::NumpadComma => Some(EvdevKey::KEY_KPCOMMA),
+            KeyCode::NumpadDecimal => Some(EvdevKey::KEY_KPDOT),
+            KeyCode::NumpadDivide => Some(EvdevKey::KEY_KPSLASH),
+            KeyCode::NumpadEnter => Some(EvdevKey::KEY_KPENTER),
+            KeyCode::NumpadEqual => Some(EvdevKey::KEY_KPEQUAL),
+            KeyCode::NumpadMultiply => Some(EvdevKey::KEY_KPASTERISK),
+            KeyCode::NumpadParenLeft => Some(EvdevKey::KEY_KPLEFTPAREN),
+            KeyCode::NumpadParenRight => Some(EvdevKey::KEY_KPRIGHTPAREN),
+            KeyCode::NumpadSubtract => Some(EvdevKey::KEY_KPMINUS),
+            KeyCode::Escape => Some(EvdevKey::KEY_ESC),
+            KeyCode::Fn => Some(EvdevKey::KEY_FN),
+            KeyCode::PrintScreen => Some(EvdevKey::KEY_SYSRQ),
+            KeyCode::ScrollLock => Some(EvdevKey::KEY_SCROLLLOCK),
+            KeyCode::Pause => Some(EvdevKey::KEY_PAUSE),
+            KeyCode::BrowserBack => Some(EvdevKey::KEY_BACK),
+            KeyCode::BrowserFavorites => Some(EvdevKey::KEY_BOOKMARKS),
+            KeyCode::BrowserForward => Some(EvdevKey::KEY_FORWARD),
+            KeyCode::BrowserHome => Some(EvdevKey::KEY_HOMEPAGE),
+            KeyCode::BrowserRefresh => Some(EvdevKey::KEY_REFRESH),
+            KeyCode::BrowserSearch => Some(EvdevKey::KEY_SEARCH),
+            KeyCode::BrowserStop => Some(EvdevKey::KEY_STOP),
+            KeyCode::Eject => Some(EvdevKey::KEY_EJECTCD),
+            KeyCode::LaunchApp1 => Some(EvdevKey::KEY_COMPUTER),
+            KeyCode::LaunchApp2 => Some(EvdevKey::KEY_CALC),
+            KeyCode::LaunchMail => Some(EvdevKey::KEY_MAIL),
+            KeyCode::MediaPlayPause => Some(EvdevKey::KEY_PLAYPAUSE),
+            KeyCode::MediaSelect => Some(EvdevKey::KEY_MEDIA),
+            KeyCode::MediaStop => Some(EvdevKey::KEY_STOPCD),
+            KeyCode::MediaTrackNext => Some(EvdevKey::KEY_NEXTSONG),
+            KeyCode::MediaTrackPrevious => Some(EvdevKey::KEY_PREVIOUSSONG),
+            KeyCode::Power => Some(EvdevKey::KEY_POWER),
+            KeyCode::Sleep => Some(EvdevKey::KEY_SLEEP),
+            KeyCode::AudioVolumeDown => Some(EvdevKey::KEY_VOLUMEDOWN),
+            KeyCode::AudioVolumeMute => Some(EvdevKey::KEY_MUTE),
+            KeyCode::AudioVolumeUp => Some(EvdevKey::KEY_VOLUMEUP),
+            KeyCode::WakeUp => Some(EvdevKey::KEY_WAKEUP),
+            KeyCode::Again => Some(EvdevKey::KEY_AGAIN),
+            KeyCode::Copy => Some(EvdevKey::KEY_COPY),
+            KeyCode::Cut => Some(EvdevKey::KEY_CUT),
+            KeyCode::Find => Some(EvdevKey::KEY_FIND),
+            KeyCode::Open => Some(EvdevKey::KEY_OPEN),
+            KeyCode::Paste => Some(EvdevKey::KEY_PASTE),
+            KeyCode::Props => Some(EvdevKey::KEY_PROPS),
+            KeyCode::Select => Some(EvdevKey::KEY_SELECT),
+            KeyCode::Undo => Some(EvdevKey::KEY_UNDO),
+            KeyCode::F1 => Some(EvdevKey::KEY_F1),
+            KeyCode::F2 => Some(EvdevKey::KEY_F2),
+            KeyCode::F3 => Some(EvdevKey::KEY_F3),
+            KeyCode::F4 => Some(EvdevKey::KEY_F4),
+            KeyCode::F5 => Some(EvdevKey::KEY_F5),
+            KeyCode::F6 => Some(EvdevKey::KEY_F6),
+            KeyCode::F7 => Some(EvdevKey::KEY_F7),
+            KeyCode::F8 => Some(EvdevKey::KEY_F8),
+            KeyCode::F9 => Some(EvdevKey::KEY_F9),
+            KeyCode::F10 => Some(EvdevKey::KEY_F10),
+            KeyCode::F11 => Some(EvdevKey::KEY_F11),
+            KeyCode::F12 => Some(EvdevKey::KEY_F12),
+            KeyCode::F13 => Some(EvdevKey::KEY_F13),
+            KeyCode::F14 => Some(EvdevKey::KEY_F14),
+            KeyCode::F15 => Some(EvdevKey::KEY_F15),
+            KeyCode::F16 => Some(EvdevKey::KEY_F16),
+            KeyCode::F17 => Some(EvdevKey::KEY_F17),
+            KeyCode::F18 => Some(EvdevKey::KEY_F18),
+            KeyCode::F19 => Some(EvdevKey::KEY_F19),
+            KeyCode::F20 => Some(EvdevKey::KEY_F20),
+            KeyCode::F21 => Some(EvdevKey::KEY_F21),
+            KeyCode::F22 => Some(EvdevKey::KEY_F22),
+            KeyCode::F23 => Some(EvdevKey::KEY_F23),
+            KeyCode::F24 => Some(EvdevKey::KEY_F24),
+            KeyCode::SuperLeft => Some(EvdevKey::KEY_LEFTMETA),
+            KeyCode::SuperRight => Some(EvdevKey::KEY_RIGHTMETA),
+            _ => None,
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn from_evdev(key: EvdevKey, dir: ElementState) -> Option<Self> {
+        let code = match key {
+            EvdevKey::KEY_GRAVE => KeyCode::Backquote,
+            EvdevKey::KEY_BACKSLASH => KeyCode::Backslash,
+            EvdevKey::KEY_LEFTBRACE => KeyCode::BracketLeft,
+            EvdevKey::KEY_RIGHTBRACE => KeyCode::BracketRight,
+            EvdevKey::KEY_COMMA => KeyCode::Comma,
+            EvdevKey::KEY_0 => KeyCode::Digit0,
+            EvdevKey::KEY_1 => KeyCode::Digit1,
+            EvdevKey::KEY_2 => KeyCode::Digit2,
+            EvdevKey::KEY_3 => KeyCode::Digit3,
+            EvdevKey::KEY_4 => KeyCode::Digit4,
+            EvdevKey::KEY_5 => KeyCode::Digit5,
+            EvdevKey::KEY_6 => KeyCode::Digit6,
+            EvdevKey::KEY_7 => KeyCode::Digit7,
+            EvdevKey::KEY_8 => KeyCode::Digit8,
+            EvdevKey::KEY_9 => KeyCode::Digit9,
+            EvdevKey::KEY_EQUAL => KeyCode::Equal,
+            EvdevKey::KEY_102ND => KeyCode::IntlBackslash,
+            EvdevKey::KEY_RO => KeyCode::IntlRo,
+            EvdevKey::KEY_YEN => KeyCode::IntlYen,
+            EvdevKey::KEY_A => KeyCode::KeyA,
+            EvdevKey::KEY_B => KeyCode::KeyB,
+            EvdevKey::KEY_C => KeyCode::KeyC,
+            EvdevKey::KEY_D => KeyCode::KeyD,
+            EvdevKey::KEY_E => KeyCode::KeyE,
+            EvdevKey::KEY_F => KeyCode::KeyF,
+            EvdevKey::KEY_G => KeyCode::KeyG,
+            EvdevKey::KEY_H => KeyCode::KeyH,
+            EvdevKey::KEY_I => KeyCode::KeyI,
+            EvdevKey::KEY_J => KeyCode::KeyJ,
+            EvdevKey::KEY_K => KeyCode::KeyK,
+            EvdevKey::KEY_L => KeyCode::KeyL,
+            EvdevKey::KEY_M => KeyCode::KeyM,
+            EvdevKey::KEY_N => KeyCode::KeyN,
+            EvdevKey::KEY_O => KeyCode::KeyO,
+            EvdevKey::KEY_P => KeyCode::KeyP,
+            EvdevKey::KEY_Q => KeyCode::KeyQ,
+            EvdevKey::KEY_R => KeyCode::KeyR,
+            EvdevKey::KEY_S => KeyCode::KeyS,
+            EvdevKey::KEY_T => KeyCode::KeyT,
+            EvdevKey::KEY_U => KeyCode::KeyU,
+            EvdevKey::KEY_V => KeyCode::KeyV,
+            EvdevKey::KEY_W => KeyCode::KeyW,
+            EvdevKey::KEY_X => KeyCode::KeyX,
+            EvdevKey::KEY_Y => KeyCode::KeyY,
+            EvdevKey::KEY_Z => KeyCode::KeyZ,
+            EvdevKey::KEY_MINUS => KeyCode::Minus,
+            EvdevKey::KEY_DOT => KeyCode::Period,
+            EvdevKey::KEY_APOSTROPHE => KeyCode::Quote,
+            EvdevKey::KEY_SEMICOLON => KeyCode::Semicolon,
+            EvdevKey::KEY_SLASH => KeyCode::Slash,
+            EvdevKey::KEY_LEFTALT => KeyCode::AltLeft,
+            EvdevKey::KEY_RIGHTALT => KeyCode::AltRight,
+            EvdevKey::KEY_BACKSPACE => KeyCode::Backspace,
+            EvdevKey::KEY_CAPSLOCK => KeyCode::CapsLock,
+            EvdevKey::KEY_COMPOSE => KeyCode::ContextMenu,
+            EvdevKey::KEY_LEFTCTRL => KeyCode::ControlLeft,
+            EvdevKey::KEY_RIGHTCTRL => KeyCode::ControlRight,
+            EvdevKey::KEY_ENTER => KeyCode::Enter,
+            EvdevKey::KEY_LEFTMETA => KeyCode::SuperLeft,
+            EvdevKey::KEY_RIGHTMETA => KeyCode::SuperRight,
+            EvdevKey::KEY_LEFTSHIFT => KeyCode::ShiftLeft,
+            EvdevKey::KEY_RIGHTSHIFT => KeyCode::ShiftRight,
+            EvdevKey::KEY_SPACE => KeyCode::Space,
+            EvdevKey::KEY_TAB => KeyCode::Tab,
+            EvdevKey::KEY_HENKAN => KeyCode::Convert,
+            EvdevKey::KEY_KATAKANAHIRAGANA => KeyCode::KanaMode,
+            EvdevKey::KEY_HANGEUL => KeyCode::Lang1,
+            EvdevKey::KEY_HANJA => KeyCode::Lang2,
+            EvdevKey::KEY_KATAKANA => KeyCode::Lang3,
+            EvdevKey::KEY_HIRAGANA => KeyCode::Lang4,
+            EvdevKey::KEY_ZENKAKUHANKAKU => KeyCode::Lang5,
+            EvdevKey::KEY_MUHENKAN => KeyCode::NonConvert,
+            EvdevKey::KEY_DELETE => KeyCode::Delete,
+            EvdevKey::KEY_END => KeyCode::End,
+            EvdevKey::KEY_HELP => KeyCode::Help,
+            EvdevKey::KEY_HOME => KeyCode::Home,
+            EvdevKey::KEY_INSERT => KeyCode::Insert,
+            EvdevKey::KEY_PAGEDOWN => KeyCode::PageDown,
+            EvdevKey::KEY_PAGEUP => KeyCode::PageUp,
+            EvdevKey::KEY_DOWN => KeyCode::ArrowDown,
+            EvdevKey::KEY_LEFT => KeyCode::ArrowLeft,
+            EvdevKey::KEY_RIGHT => KeyCode::ArrowRight,
+            EvdevKey::KEY_UP => KeyCode::ArrowUp,
+            EvdevKey::KEY_NUMLOCK => KeyCode::NumLock,
+            EvdevKey::KEY_KP0 => KeyCode::Numpad0,
+            EvdevKey::KEY_KP1 => KeyCode::Numpad1,
+            EvdevKey::KEY_KP2 => KeyCode::Numpad2,
+            EvdevKey::KEY_KP3 => KeyCode::Numpad3,
+            EvdevKey::KEY_KP4 => KeyCode::Numpad4,
+            EvdevKey::KEY_KP5 => KeyCode::Numpad5,
+            EvdevKey::KEY_KP6 => KeyCode::Numpad6,
+            EvdevKey::KEY_KP7 => KeyCode::Numpad7,
+            EvdevKey::KEY_KP8 => KeyCode::Numpad8,
+            EvdevKey::KEY_KP9 => KeyCode::Numpad9,
+            EvdevKey::KEY_KPPLUS => KeyCode::NumpadAdd,
+            EvdevKey::KEY_KPCOMMA => KeyCode::NumpadComma,
+            EvdevKey::KEY_KPDOT => KeyCode::NumpadDecimal,
+            EvdevKey::KEY_KPSLASH => KeyCode::NumpadDivide,
+            EvdevKey::KEY_KPENTER => KeyCode::NumpadEnter,
+            EvdevKey::KEY_KPEQUAL => KeyCode::NumpadEqual,
+            EvdevKey::KEY_KPASTERISK => KeyCode::NumpadMultiply,
+            EvdevKey::KEY_KPLEFTPAREN => KeyCode::NumpadParenLeft,
+            EvdevKey::KEY_KPRIGHTPAREN => KeyCode::NumpadParenRight,
+            EvdevKey::KEY_KPMINUS => KeyCode::NumpadSubtract,
+            EvdevKey::KEY_ESC => KeyCode::Escape,
+            EvdevKey::KEY_FN => KeyCode::Fn,
+            EvdevKey::KEY_SYSRQ => KeyCode::PrintScreen,
+            EvdevKey::KEY_SCROLLLOCK => KeyCode::ScrollLock,
+            EvdevKey::KEY_PAUSE => KeyCode::Pause,
+            EvdevKey::KEY_BACK => KeyCode::BrowserBack,
+            EvdevKey::KEY_BOOKMARKS => KeyCode::BrowserFavorites,
+            EvdevKey::KEY_FORWARD => KeyCode::BrowserForward,
+            EvdevKey::KEY_HOMEPAGE => KeyCode::BrowserHome,
+            EvdevKey::KEY_REFRESH => KeyCode::BrowserRefresh,
+            EvdevKey::KEY_SEARCH => KeyCode::BrowserSearch,
+            EvdevKey::KEY_STOP => KeyCode::BrowserStop,
+            EvdevKey::KEY_EJECTCD => KeyCode::Eject,
+            EvdevKey::KEY_COMPUTER => KeyCode::LaunchApp1,
+            EvdevKey::KEY_CALC => KeyCode::LaunchApp2,
+            EvdevKey::KEY_MAIL => KeyCode::LaunchMail,
+            EvdevKey::KEY_PLAYPAUSE => KeyCode::MediaPlayPause,
+            EvdevKey::KEY_MEDIA => KeyCode::MediaSelect,
+            EvdevKey::KEY_STOPCD => KeyCode::MediaStop,
+            EvdevKey::KEY_NEXTSONG => KeyCode::MediaTrackNext,
+            EvdevKey::KEY_PREVIOUSSONG => KeyCode::MediaTrackPrevious,
+            EvdevKey::KEY_POWER => KeyCode::Power,
+            EvdevKey::KEY_SLEEP => KeyCode::Sleep,
+            EvdevKey::KEY_VOLUMEDOWN => KeyCode::AudioVolumeDown,
+            EvdevKey::KEY_MUTE => KeyCode::AudioVolumeMute,
+            EvdevKey::KEY_VOLUMEUP => KeyCode::AudioVolumeUp,
+            EvdevKey::KEY_WAKEUP => KeyCode::WakeUp,
+            EvdevKey::KEY_AGAIN => KeyCode::Again,
+            EvdevKey::KEY_COPY => KeyCode::Copy,
+            EvdevKey::KEY_CUT => KeyCode::Cut,
+            EvdevKey::KEY_FIND => KeyCode::Find,
+            EvdevKey::KEY_OPEN => KeyCode::Open,
+            EvdevKey::KEY_PASTE => KeyCode::Paste,
+            EvdevKey::KEY_PROPS => KeyCode::Props,
+            EvdevKey::KEY_SELECT => KeyCode::Select,
+            EvdevKey::KEY_UNDO => KeyCode::Undo,
+            EvdevKey::KEY_F1 => KeyCode::F1,
+            EvdevKey::KEY_F2 => KeyCode::F2,
+            EvdevKey::KEY_F3 => KeyCode::F3,
+            EvdevKey::KEY_F4 => KeyCode::F4,
+            EvdevKey::KEY_F5 => KeyCode::F5,
+            EvdevKey::KEY_F6 => KeyCode::F6,
+            EvdevKey::KEY_F7 => KeyCode::F7,
+            EvdevKey::KEY_F8 => KeyCode::F8,
+            EvdevKey::KEY_F9 => KeyCode::F9,
+            EvdevKey::KEY_F10 => KeyCode::F10,
+            EvdevKey::KEY_F11 => KeyCode::F11,
+            EvdevKey::KEY_F12 => KeyCode::F12,
+            EvdevKey::KEY_F13 => KeyCode::F13,
+            EvdevKey::KEY_F14 => KeyCode::F14,
+            EvdevKey::KEY_F15 => KeyCode::F15,
+            EvdevKey::KEY_F16 => KeyCode::F16,
+            EvdevKey::KEY_F17 => KeyCode::F17,
+            EvdevKey::KEY_F18 => KeyCode::F18,
+            EvdevKey::KEY_F19 => KeyCode::F19,
+            EvdevKey::KEY_F20 => KeyCode::F20,
+            EvdevKey::KEY_F21 => KeyCode::F21,
+            EvdevKey::KEY_F22 => KeyCode::F22,
+            EvdevKey::KEY_F23 => KeyCode::F23,
+            EvdevKey::KEY_F24 => KeyCode::F24,
+            _ => return None,
+        };
+        Some(Self::new(code, dir))
+    }
+}
+
+/// Looks up the key, and whether shift has to be held, that types `c` on a US layout.
+/// Used by backends that can only send key presses to type text
+pub fn char_to_hid(c: char) -> Option<(HidCodes, bool)> {
+    let key = match c.to_ascii_lowercase() {
+        'a' => HidCodes::KeyboardAa,
+        'b' => HidCodes::KeyboardBb,
+        'c' => HidCodes::KeyboardCc,
+        'd' => HidCodes::KeyboardDd,
+        'e' => HidCodes::KeyboardEe,
+        'f' => HidCodes::KeyboardFf,
+        'g' => HidCodes::KeyboardGg,
+        'h' => HidCodes::KeyboardHh,
+        'i' => HidCodes::KeyboardIi,
+        'j' => HidCodes::KeyboardJj,
+        'k' => HidCodes::KeyboardKk,
+        'l' => HidCodes::KeyboardLl,
+        'm' => HidCodes::KeyboardMm,
+        'n' => HidCodes::KeyboardNn,
+        'o' => HidCodes::KeyboardOo,
+        'p' => HidCodes::KeyboardPp,
+        'q' => HidCodes::KeyboardQq,
+        'r' => HidCodes::KeyboardRr,
+        's' => HidCodes::KeyboardSs,
+        't' => HidCodes::KeyboardTt,
+        'u' => HidCodes::KeyboardUu,
+        'v' => HidCodes::KeyboardVv,
+        'w' => HidCodes::KeyboardWw,
+        'x' => HidCodes::KeyboardXx,
+        'y' => HidCodes::KeyboardYy,
+        'z' => HidCodes::KeyboardZz,
+        _ => {
+            return match c {
+                '1' => Some((HidCodes::Keyboard1Exclamation, false)),
+                '!' => Some((HidCodes::Keyboard1Exclamation, true)),
+                '2' => Some((HidCodes::Keyboard2At, false)),
+                '@' => Some((HidCodes::Keyboard2At, true)),
+                '3' => Some((HidCodes::Keyboard3Hash, false)),
+                '#' => Some((HidCodes::Keyboard3Hash, true)),
+                '4' => Some((HidCodes::Keyboard4Dollar, false)),
+                '$' => Some((HidCodes::Keyboard4Dollar, true)),
+                '5' => Some((HidCodes::Keyboard5Percent, false)),
+                '%' => Some((HidCodes::Keyboard5Percent, true)),
+                '6' => Some((HidCodes::Keyboard6Caret, false)),
+                '^' => Some((HidCodes::Keyboard6Caret, true)),
+                '7' => Some((HidCodes::Keyboard7Ampersand, false)),
+                '&' => Some((HidCodes::Keyboard7Ampersand, true)),
+                '8' => Some((HidCodes::Keyboard8Asterisk, false)),
+                '*' => Some((HidCodes::Keyboard8Asterisk, true)),
+                '9' => Some((HidCodes::Keyboard9OpenParens, false)),
+                '(' => Some((HidCodes::Keyboard9OpenParens, true)),
+                '0' => Some((HidCodes::Keyboard0CloseParens, false)),
+                ')' => Some((HidCodes::Keyboard0CloseParens, true)),
+                '\n' => Some((HidCodes::KeyboardEnter, false)),
+                '\t' => Some((HidCodes::KeyboardTab, false)),
+                ' ' => Some((HidCodes::KeyboardSpacebar, false)),
+                '-' => Some((HidCodes::KeyboardDashUnderscore, false)),
+                '_' => Some((HidCodes::KeyboardDashUnderscore, true)),
+                '=' => Some((HidCodes::KeyboardEqualPlus, false)),
+                '+' => Some((HidCodes::KeyboardEqualPlus, true)),
+                '[' => Some((HidCodes::KeyboardOpenBracketBrace, false)),
+                '{' => Some((HidCodes::KeyboardOpenBracketBrace, true)),
+                ']' => Some((HidCodes::KeyboardCloseBracketBrace, false)),
+                '}' => Some((HidCodes::KeyboardCloseBracketBrace, true)),
+                '\\' => Some((HidCodes::KeyboardBackslashBar, false)),
+                '|' => Some((HidCodes::KeyboardBackslashBar, true)),
+                ';' => Some((HidCodes::KeyboardSemiColon, false)),
+                ':' => Some((HidCodes::KeyboardSemiColon, true)),
+                '\'' => Some((HidCodes::KeyboardSingleDoubleQuote, false)),
+                '"' => Some((HidCodes::KeyboardSingleDoubleQuote, true)),
+                '`' => Some((HidCodes::KeyboardBacktickTilde, false)),
+                '~' => Some((HidCodes::KeyboardBacktickTilde, true)),
+                ',' => Some((HidCodes::KeyboardCommaLess, false)),
+                '<' => Some((HidCodes::KeyboardCommaLess, true)),
+                '.' => Some((HidCodes::KeyboardPeriodGreater, false)),
+                '>' => Some((HidCodes::KeyboardPeriodGreater, true)),
+                '/' => Some((HidCodes::KeyboardSlashQuestion, false)),
+                '?' => Some((HidCodes::KeyboardSlashQuestion, true)),
+                _ => None,
+            };
+        }
+    };
+    Some((key, c.is_ascii_uppercase()))
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -364,17 +1181,57 @@ impl MouseButtons {
         Self { button, dir }
     }
 
-    pub fn to_hid(&self) -> HidCodes {
+    pub fn button(&self) -> MouseButton {
+        self.button
+    }
+
+    pub fn to_hid(&self) -> Option<HidCodes> {
+        match self.button {
+            MouseButton::Left => Some(HidCodes::MouseLeftClick),
+            MouseButton::Right => Some(HidCodes::MouseRightClick),
+            MouseButton::Middle => Some(HidCodes::MouseMiddleClick),
+            MouseButton::Back => Some(HidCodes::Mouse4),
+            MouseButton::Forward => Some(HidCodes::Mouse5),
+            MouseButton::Other(_) => None,
+        }
+    }
+
+    pub fn to_enigo(&self) -> Option<enigo::Button> {
         match self.button {
-            MouseButton::Left => HidCodes::MouseLeftClick,
-            MouseButton::Right => HidCodes::MouseRightClick,
-            MouseButton::Middle => HidCodes::MouseMiddleClick,
-            MouseButton::Back => HidCodes::Mouse4,
-            MouseButton::Forward => HidCodes::Mouse5,
-            MouseButton::Other(_) => todo!("Mouse button not handled"),
+            MouseButton::Left => Some(enigo::Button::Left),
+            MouseButton::Right => Some(enigo::Button::Right),
+            MouseButton::Middle => Some(enigo::Button::Middle),
+            MouseButton::Back => Some(enigo::Button::Back),
+            MouseButton::Forward => Some(enigo::Button::Forward),
+            MouseButton::Other(_) => None,
         }
     }
 
+    #[cfg(target_os = "linux")]
+    pub fn to_evdev(&self) -> Option<EvdevKey> {
+        match self.button {
+            MouseButton::Left => Some(EvdevKey::BTN_LEFT),
+            MouseButton::Right => Some(EvdevKey::BTN_RIGHT),
+            MouseButton::Middle => Some(EvdevKey::BTN_MIDDLE),
+            MouseButton::Back => Some(EvdevKey::BTN_SIDE),
+            MouseButton::Forward => Some(EvdevKey::BTN_EXTRA),
+            MouseButton::Other(_) => None,
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn from_evdev(key: EvdevKey, dir: ElementState) -> Option<Self> {
+        let button = match key {
+            EvdevKey::BTN_LEFT => MouseButton::Left,
+            EvdevKey::BTN_RIGHT => MouseButton::Right,
+            EvdevKey::BTN_MIDDLE => MouseButton::Middle,
+            EvdevKey::BTN_SIDE | EvdevKey::BTN_BACK => MouseButton::Back,
+            EvdevKey::BTN_EXTRA | EvdevKey::BTN_FORWARD => MouseButton::Forward,
+            _ => return None,
+        };
+        Some(Self::new(button, dir))
+    }
+
     #[cfg(target_os = "windows")]
     pub fn to_winput(&self) -> winput::Button {
         use winput::Button;
@@ -389,3 +1246,267 @@ impl MouseButtons {
         button
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    /// Every variant of winit 0.30's `KeyCode`. The enum is non-exhaustive, so a match
+    /// can't catch a missing arm and this list stands in for it
+    pub(crate) const ALL_KEYS: [KeyCode; 194] = [
+        KeyCode::Backquote,
+        KeyCode::Backslash,
+        KeyCode::BracketLeft,
+        KeyCode::BracketRight,
+        KeyCode::Comma,
+        KeyCode::Digit0,
+        KeyCode::Digit1,
+        KeyCode::Digit2,
+        KeyCode::Digit3,
+        KeyCode::Digit4,
+        KeyCode::Digit5,
+        KeyCode::Digit6,
+        KeyCode::Digit7,
+        KeyCode::Digit8,
+        KeyCode::Digit9,
+        KeyCode::Equal,
+        KeyCode::IntlBackslash,
+        KeyCode::IntlRo,
+        KeyCode::IntlYen,
+        KeyCode::KeyA,
+        KeyCode::KeyB,
+        KeyCode::KeyC,
+        KeyCode::KeyD,
+        KeyCode::KeyE,
+        KeyCode::KeyF,
+        KeyCode::KeyG,
+        KeyCode::KeyH,
+        KeyCode::KeyI,
+        KeyCode::KeyJ,
+        KeyCode::KeyK,
+        KeyCode::KeyL,
+        KeyCode::KeyM,
+        KeyCode::KeyN,
+        KeyCode::KeyO,
+        KeyCode::KeyP,
+        KeyCode::KeyQ,
+        KeyCode::KeyR,
+        KeyCode::KeyS,
+        KeyCode::KeyT,
+        KeyCode::KeyU,
+        KeyCode::KeyV,
+        KeyCode::KeyW,
+        KeyCode::KeyX,
+        KeyCode::KeyY,
+        KeyCode::KeyZ,
+        KeyCode::Minus,
+        KeyCode::Period,
+        KeyCode::Quote,
+        KeyCode::Semicolon,
+        KeyCode::Slash,
+        KeyCode::AltLeft,
+        KeyCode::AltRight,
+        KeyCode::Backspace,
+        KeyCode::CapsLock,
+        KeyCode::ContextMenu,
+        KeyCode::ControlLeft,
+        KeyCode::ControlRight,
+        KeyCode::Enter,
+        KeyCode::SuperLeft,
+        KeyCode::SuperRight,
+        KeyCode::ShiftLeft,
+        KeyCode::ShiftRight,
+        KeyCode::Space,
+        KeyCode::Tab,
+        KeyCode::Convert,
+        KeyCode::KanaMode,
+        KeyCode::Lang1,
+        KeyCode::Lang2,
+        KeyCode::Lang3,
+        KeyCode::Lang4,
+        KeyCode::Lang5,
+        KeyCode::NonConvert,
+        KeyCode::Delete,
+        KeyCode::End,
+        KeyCode::Help,
+        KeyCode::Home,
+        KeyCode::Insert,
+        KeyCode::PageDown,
+        KeyCode::PageUp,
+        KeyCode::ArrowDown,
+        KeyCode::ArrowLeft,
+        KeyCode::ArrowRight,
+        KeyCode::ArrowUp,
+        KeyCode::NumLock,
+        KeyCode::Numpad0,
+        KeyCode::Numpad1,
+        KeyCode::Numpad2,
+        KeyCode::Numpad3,
+        KeyCode::Numpad4,
+        KeyCode::Numpad5,
+        KeyCode::Numpad6,
+        KeyCode::Numpad7,
+        KeyCode::Numpad8,
+        KeyCode::Numpad9,
+        KeyCode::NumpadAdd,
+        KeyCode::NumpadBackspace,
+        KeyCode::NumpadClear,
+        KeyCode::NumpadClearEntry,
+        KeyCode::NumpadComma,
+        KeyCode::NumpadDecimal,
+        KeyCode::NumpadDivide,
+        KeyCode::NumpadEnter,
+        KeyCode::NumpadEqual,
+        KeyCode::NumpadHash,
+        KeyCode::NumpadMemoryAdd,
+        KeyCode::NumpadMemoryClear,
+        KeyCode::NumpadMemoryRecall,
+        KeyCode::NumpadMemoryStore,
+        KeyCode::NumpadMemorySubtract,
+        KeyCode::NumpadMultiply,
+        KeyCode::NumpadParenLeft,
+        KeyCode::NumpadParenRight,
+        KeyCode::NumpadStar,
+        KeyCode::NumpadSubtract,
+        KeyCode::Escape,
+        KeyCode::Fn,
+        KeyCode::FnLock,
+        KeyCode::PrintScreen,
+        KeyCode::ScrollLock,
+        KeyCode::Pause,
+        KeyCode::BrowserBack,
+        KeyCode::BrowserFavorites,
+        KeyCode::BrowserForward,
+        KeyCode::BrowserHome,
+        KeyCode::BrowserRefresh,
+        KeyCode::BrowserSearch,
+        KeyCode::BrowserStop,
+        KeyCode::Eject,
+        KeyCode::LaunchApp1,
+        KeyCode::LaunchApp2,
+        KeyCode::LaunchMail,
+        KeyCode::MediaPlayPause,
+        KeyCode::MediaSelect,
+        KeyCode::MediaStop,
+        KeyCode::MediaTrackNext,
+        KeyCode::MediaTrackPrevious,
+        KeyCode::Power,
+        KeyCode::Sleep,
+        KeyCode::AudioVolumeDown,
+        KeyCode::AudioVolumeMute,
+        KeyCode::AudioVolumeUp,
+        KeyCode::WakeUp,
+        KeyCode::Meta,
+        KeyCode::Hyper,
+        KeyCode::Turbo,
+        KeyCode::Abort,
+        KeyCode::Resume,
+        KeyCode::Suspend,
+        KeyCode::Again,
+        KeyCode::Copy,
+        KeyCode::Cut,
+        KeyCode::Find,
+        KeyCode::Open,
+        KeyCode::Paste,
+        KeyCode::Props,
+        KeyCode::Select,
+        KeyCode::Undo,
+        KeyCode::Hiragana,
+        KeyCode::Katakana,
+        KeyCode::F1,
+        KeyCode::F2,
+        KeyCode::F3,
+        KeyCode::F4,
+        KeyCode::F5,
+        KeyCode::F6,
+        KeyCode::F7,
+        KeyCode::F8,
+        KeyCode::F9,
+        KeyCode::F10,
+        KeyCode::F11,
+        KeyCode::F12,
+        KeyCode::F13,
+        KeyCode::F14,
+        KeyCode::F15,
+        KeyCode::F16,
+        KeyCode::F17,
+        KeyCode::F18,
+        KeyCode::F19,
+        KeyCode::F20,
+        KeyCode::F21,
+        KeyCode::F22,
+        KeyCode::F23,
+        KeyCode::F24,
+        KeyCode::F25,
+        KeyCode::F26,
+        KeyCode::F27,
+        KeyCode::F28,
+        KeyCode::F29,
+        KeyCode::F30,
+        KeyCode::F31,
+        KeyCode::F32,
+        KeyCode::F33,
+        KeyCode::F34,
+        KeyCode::F35,
+    ];
+
+    /// Keys without a keyboard page usage
+    const NO_KEYBOARD_USAGE: &[KeyCode] = &[
+        KeyCode::Fn,
+        KeyCode::FnLock,
+        KeyCode::BrowserBack,
+        KeyCode::BrowserFavorites,
+        KeyCode::BrowserForward,
+        KeyCode::BrowserHome,
+        KeyCode::BrowserRefresh,
+        KeyCode::BrowserSearch,
+        KeyCode::BrowserStop,
+        KeyCode::Eject,
+        KeyCode::LaunchApp1,
+        KeyCode::LaunchApp2,
+        KeyCode::LaunchMail,
+        KeyCode::MediaPlayPause,
+        KeyCode::MediaSelect,
+        KeyCode::MediaStop,
+        KeyCode::MediaTrackNext,
+        KeyCode::MediaTrackPrevious,
+        KeyCode::Sleep,
+        KeyCode::WakeUp,
+        KeyCode::Meta,
+        KeyCode::Hyper,
+        KeyCode::Turbo,
+        KeyCode::Resume,
+        KeyCode::Suspend,
+        KeyCode::F25,
+        KeyCode::F26,
+        KeyCode::F27,
+        KeyCode::F28,
+        KeyCode::F29,
+        KeyCode::F30,
+        KeyCode::F31,
+        KeyCode::F32,
+        KeyCode::F33,
+        KeyCode::F34,
+        KeyCode::F35,
+    ];
+
+    #[test]
+    fn every_key_with_a_usage_maps() {
+        assert_eq!(
+            ALL_KEYS.iter().collect::<HashSet<_>>().len(),
+            ALL_KEYS.len()
+        );
+        for code in ALL_KEYS {
+            let hid = ScanCode::new(code, ElementState::Pressed).to_hid();
+            assert_eq!(
+                hid.is_none(),
+                NO_KEYBOARD_USAGE.contains(&code),
+                "{:?} maps to {:?}",
+                code,
+                hid
+            );
+        }
+    }
+}
@@ -1,18 +1,126 @@
 use std::{
+    collections::{HashMap, HashSet},
+    fmt,
     ops::Deref,
-    sync::{Arc, atomic::AtomicBool},
-    time::Duration,
+    sync::{Arc, Weak},
+    time::{Duration, Instant},
 };
 
-use tokio::{runtime::Handle, sync::Mutex, time::sleep};
-use winit::event::ElementState;
+use enigo::{Axis, Coordinate, Direction, Enigo, Keyboard, Mouse, NewConError, Settings};
+use tokio::{
+    runtime::Handle,
+    select,
+    sync::{Mutex, Notify, watch},
+    task::JoinHandle,
+    time::sleep,
+};
+use winit::{
+    event::{ElementState, MouseButton},
+    keyboard::KeyCode,
+};
+
+use crate::{
+    codes::{EmulatorStatus, HidEvent, LockState, ScanCode, char_to_hid},
+    queue::PendingQueue,
+    report::{self, Capabilities, ConsumerReport, KeyboardReport, MouseReport, ReportMode},
+    scan_codes::HidCodes,
+    state::InputState,
+    transport::{HidApiTransport, HidTransport, HidWrite},
+};
 
-use hidapi::{DeviceInfo, HidApi, HidDevice};
+#[cfg(target_os = "linux")]
+use crate::codes::{GamepadButton, GamepadState, TouchContact};
+#[cfg(target_os = "linux")]
+use evdev::{
+    AbsInfo, AbsoluteAxisCode, AttributeSet, BusType, EventType, InputEvent, InputId,
+    KeyCode as EvdevKey, PropType, RelativeAxisCode, UinputAbsSetup, uinput::VirtualDevice,
+};
+#[cfg(target_os = "linux")]
+use std::collections::hash_map::Entry;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EmulatorError {
+    /// The output device isn't there, the event was dropped
+    Unavailable,
+    /// The backend can't emulate this at all, another one may
+    Unsupported(String),
+    /// The backend failed to inject the event
+    Failed(String),
+}
 
-use crate::codes::HidEvent;
+impl fmt::Display for EmulatorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmulatorError::Unavailable => write!(f, "output device unavailable"),
+            EmulatorError::Unsupported(e) => write!(f, "unsupported by the backend: {}", e),
+            EmulatorError::Failed(e) => write!(f, "failed to emulate input: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for EmulatorError {}
+
+fn failed(e: impl fmt::Display) -> EmulatorError {
+    EmulatorError::Failed(e.to_string())
+}
 
 pub trait Emulator {
-    fn emulate_input(&self, hid_event: &HidEvent);
+    fn emulate_input(&self, hid_event: &HidEvent) -> Result<(), EmulatorError>;
+
+    /// Like [`Emulator::emulate_input`], but an event the backend can't take right now
+    /// isn't kept for later, for when another backend may take it instead
+    fn try_input(&self, hid_event: &HidEvent) -> Result<(), EmulatorError> {
+        self.emulate_input(hid_event)
+    }
+
+    /// Keeps an event that no backend could take, to replay once the device is back
+    fn defer(&self, _hid_event: &HidEvent) {}
+
+    /// Types `text` on the target regardless of its keyboard layout. Defaults to enigo's
+    /// unicode injection
+    fn emulate_text(&self, text: &str) -> Result<(), EmulatorError> {
+        let mut enigo = Enigo::new(&Settings::default()).map_err(failed)?;
+        enigo.text(text).map_err(failed)
+    }
+
+    /// The key presses and releases that type `text`, for backends that can only type
+    /// through keys, so text can be paced per report. `None` for backends that inject
+    /// text of their own
+    fn text_keys(&self, _text: &str) -> Option<Result<Vec<HidEvent>, EmulatorError>> {
+        None
+    }
+
+    /// Follows the state of the output device. Backends that can't lose their device are
+    /// always connected
+    fn status(&self) -> watch::Receiver<EmulatorStatus> {
+        watch::channel(EmulatorStatus::Connected).1
+    }
+
+    /// Follows the target's Caps, Num and Scroll Lock. `None` if the backend can't see them
+    fn locks(&self) -> Option<watch::Receiver<LockState>> {
+        None
+    }
+}
+
+/// The presses and releases that type `text` on a US layout, shift included. Text with
+/// a character no key types is unsupported as a whole
+pub(crate) fn us_layout_keys(text: &str) -> Result<Vec<HidEvent>, EmulatorError> {
+    let key = |code, dir| HidEvent::Key(ScanCode::new(code, dir));
+    let mut keys = Vec::new();
+    for c in text.chars() {
+        let (code, shift) = char_to_hid(c)
+            .and_then(|(code, shift)| Some((code.to_key_code()?, shift)))
+            .ok_or_else(|| EmulatorError::Unsupported(format!("no US layout key types {:?}", c)))?;
+        if shift {
+            keys.push(key(KeyCode::ShiftLeft, ElementState::Pressed));
+        }
+        keys.push(key(code, ElementState::Pressed));
+        keys.push(key(code, ElementState::Released));
+        if shift {
+            keys.push(key(KeyCode::ShiftLeft, ElementState::Released));
+        }
+    }
+    Ok(keys)
 }
 
 pub struct WinputEmulator;
@@ -25,7 +133,7 @@ impl WinputEmulator {
 
 #[cfg(target_os = "windows")]
 impl Emulator for WinputEmulator {
-    fn emulate_input(&self, hid_event: &HidEvent) {
+    fn emulate_input(&self, hid_event: &HidEvent) -> Result<(), EmulatorError> {
         match hid_event {
             HidEvent::Key(scan_code) => {
                 if let Some(code) = scan_code.to_winput() {
@@ -46,138 +154,1772 @@ impl Emulator for WinputEmulator {
             HidEvent::MouseScroll(scroll) => {
                 winput::Mouse::scroll(*scroll as f32);
             }
+            HidEvent::MouseAbsolute { x, y } => {
+                winput::Mouse::move_absolute(
+                    *x as f32 / u16::MAX as f32,
+                    *y as f32 / u16::MAX as f32,
+                );
+            }
+            // winput has no pointer injection for touch or pen input, nor virtual controllers
+            HidEvent::Touch(_) | HidEvent::Pen(_) => {
+                return Err(EmulatorError::Unsupported(
+                    "touch and pen input need the USB dongle".to_string(),
+                ));
+            }
+            HidEvent::Gamepad(_) => {
+                return Err(EmulatorError::Unsupported(
+                    "winput can't inject gamepads".to_string(),
+                ));
+            }
         };
+        Ok(())
+    }
+
+    fn emulate_text(&self, text: &str) -> Result<(), EmulatorError> {
+        winput::send_str(text);
+        Ok(())
+    }
+}
+
+/// Cross-platform software backend for servers without the USB dongle
+pub struct EnigoEmulator {
+    // Enigo needs `&mut self` for every call while `Emulator` hands out `&self`
+    enigo: std::sync::Mutex<Enigo>,
+}
+
+impl EnigoEmulator {
+    /// Fails on hosts enigo can't inject into, e.g. without an X server
+    pub fn new() -> Result<Self, NewConError> {
+        Ok(EnigoEmulator {
+            enigo: std::sync::Mutex::new(Enigo::new(&Settings::default())?),
+        })
+    }
+}
+
+impl Emulator for EnigoEmulator {
+    fn emulate_input(&self, hid_event: &HidEvent) -> Result<(), EmulatorError> {
+        let mut enigo = self.enigo.lock().unwrap();
+        match hid_event {
+            HidEvent::Key(scan_code) => match scan_code.to_enigo() {
+                Some(key) => enigo.key(key, to_direction(scan_code.dir)),
+                None => Ok(()),
+            },
+            HidEvent::MouseDelta(x, y) => enigo.move_mouse(*x, *y, Coordinate::Rel),
+            HidEvent::MouseButton(mouse_buttons) => match mouse_buttons.to_enigo() {
+                Some(button) => enigo.button(button, to_direction(mouse_buttons.dir)),
+                None => Ok(()),
+            },
+            // Positive scroll is up on the wire while enigo scrolls down for positive lengths
+            HidEvent::MouseScroll(scroll) => enigo.scroll(-*scroll, Axis::Vertical),
+            HidEvent::MouseAbsolute { x, y } => enigo.main_display().and_then(|(w, h)| {
+                let scale = |pos: u16, len: i32| pos as i64 * (len - 1) as i64 / u16::MAX as i64;
+                enigo.move_mouse(scale(*x, w) as i32, scale(*y, h) as i32, Coordinate::Abs)
+            }),
+            HidEvent::Touch(_) | HidEvent::Pen(_) => {
+                return Err(EmulatorError::Unsupported(
+                    "enigo can't inject touch or pen input".to_string(),
+                ));
+            }
+            HidEvent::Gamepad(_) => {
+                return Err(EmulatorError::Unsupported(
+                    "enigo can't inject gamepads".to_string(),
+                ));
+            }
+        }
+        .map_err(failed)
+    }
+
+    fn emulate_text(&self, text: &str) -> Result<(), EmulatorError> {
+        self.enigo.lock().unwrap().text(text).map_err(failed)
+    }
+}
+
+fn to_direction(dir: ElementState) -> Direction {
+    match dir {
+        ElementState::Pressed => Direction::Press,
+        ElementState::Released => Direction::Release,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedEvent {
+    pub at: Instant,
+    pub event: HidEvent,
+}
+
+#[derive(Default)]
+struct Recording {
+    events: Vec<RecordedEvent>,
+    text: String,
+}
+
+/// Emulator for tests that records everything it's asked to emulate instead of touching
+/// the OS or any hardware
+#[derive(Default)]
+pub struct RecordingEmulator {
+    recording: std::sync::Mutex<Recording>,
+    notify: Notify,
+}
+
+impl RecordingEmulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn recorded(&self) -> Vec<RecordedEvent> {
+        self.recording.lock().unwrap().events.clone()
+    }
+
+    pub fn events(&self) -> Vec<HidEvent> {
+        let recording = self.recording.lock().unwrap();
+        recording
+            .events
+            .iter()
+            .map(|recorded| recorded.event)
+            .collect()
+    }
+
+    /// Everything passed to `emulate_text` so far, concatenated
+    pub fn text(&self) -> String {
+        self.recording.lock().unwrap().text.clone()
+    }
+
+    /// Keys and buttons left held down by the recorded events
+    pub fn state(&self) -> InputState {
+        let mut state = InputState::new();
+        for recorded in self.recording.lock().unwrap().events.iter() {
+            state.apply(&recorded.event);
+        }
+        state
+    }
+
+    pub fn clear(&self) {
+        let mut recording = self.recording.lock().unwrap();
+        recording.events.clear();
+        recording.text.clear();
+    }
+
+    /// Waits until at least `count` events were recorded, returning false on timeout
+    pub async fn wait_for_events(&self, count: usize, timeout: Duration) -> bool {
+        tokio::time::timeout(timeout, async {
+            loop {
+                let notified = self.notify.notified();
+                if self.recording.lock().unwrap().events.len() >= count {
+                    return;
+                }
+                notified.await;
+            }
+        })
+        .await
+        .is_ok()
     }
+
+    #[track_caller]
+    pub fn assert_events(&self, expected: &[HidEvent]) {
+        assert_eq!(self.events(), expected, "recorded events differ");
+    }
+
+    /// Checks that `expected` was recorded in order, with anything allowed in between
+    #[track_caller]
+    pub fn assert_contains_sequence(&self, expected: &[HidEvent]) {
+        let events = self.events();
+        let mut remaining = events.iter();
+        for event in expected {
+            assert!(
+                remaining.any(|recorded| recorded == event),
+                "{:?} missing from the recorded sequence {:?}",
+                event,
+                events
+            );
+        }
+    }
+
+    /// Checks that exactly `keys` are left held down
+    #[track_caller]
+    pub fn assert_keys_held(&self, keys: &[KeyCode]) {
+        let state = self.state();
+        let expected: HashSet<KeyCode> = keys.iter().copied().collect();
+        assert_eq!(state.keys(), &expected, "held keys differ");
+    }
+
+    /// Checks that every key and mouse button pressed was released again
+    #[track_caller]
+    pub fn assert_all_released(&self) {
+        let state = self.state();
+        assert!(state.is_empty(), "still held: {:?}", state);
+    }
+}
+
+impl Emulator for RecordingEmulator {
+    fn emulate_input(&self, hid_event: &HidEvent) -> Result<(), EmulatorError> {
+        self.recording.lock().unwrap().events.push(RecordedEvent {
+            at: Instant::now(),
+            event: *hid_event,
+        });
+        self.notify.notify_waiters();
+        Ok(())
+    }
+
+    fn emulate_text(&self, text: &str) -> Result<(), EmulatorError> {
+        self.recording.lock().unwrap().text.push_str(text);
+        self.notify.notify_waiters();
+        Ok(())
+    }
+}
+
+/// What a [`CompositeEmulator`] routes on and a
+/// [`PacedEmulator`](crate::pacing::PacedEmulator) paces by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    Keyboard,
+    /// Motion, buttons and scroll
+    Mouse,
+    Touch,
+    Pen,
+    Gamepad,
+    Text,
 }
 
+impl EventKind {
+    pub fn of(hid_event: &HidEvent) -> Self {
+        match hid_event {
+            HidEvent::Key(_) => EventKind::Keyboard,
+            HidEvent::MouseDelta(..)
+            | HidEvent::MouseButton(_)
+            | HidEvent::MouseScroll(_)
+            | HidEvent::MouseAbsolute { .. } => EventKind::Mouse,
+            HidEvent::Touch(_) => EventKind::Touch,
+            HidEvent::Pen(_) => EventKind::Pen,
+            HidEvent::Gamepad(_) => EventKind::Gamepad,
+        }
+    }
+}
+
+/// A key or button whose release has to go where its press went
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Press {
+    Key(KeyCode),
+    Button(MouseButton),
+}
+
+impl Press {
+    fn of(hid_event: &HidEvent) -> Option<(Self, ElementState)> {
+        match hid_event {
+            HidEvent::Key(scan_code) => Some((Press::Key(scan_code.code()), scan_code.dir)),
+            HidEvent::MouseButton(mouse_buttons) => {
+                Some((Press::Button(mouse_buttons.button()), mouse_buttons.dir))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Connected while any backend is, since the others only stand in for it. Otherwise
+/// whatever the first backend is doing
+fn combined_status(statuses: &[watch::Receiver<EmulatorStatus>]) -> EmulatorStatus {
+    let mut statuses = statuses.iter().map(|status| status.borrow().clone());
+    let first = statuses.next().unwrap_or(EmulatorStatus::Connected);
+    if statuses.any(|status| status == EmulatorStatus::Connected) {
+        return EmulatorStatus::Connected;
+    }
+    first
+}
+
+/// Spreads events over several backends, e.g. the keyboard on the dongle and the mouse
+/// on the OS injector.
+///
+/// Every kind of event goes through a list of backends in order of preference, moving
+/// on to the next one whenever a backend reports [`EmulatorError::Unavailable`] or
+/// [`EmulatorError::Unsupported`]. Kinds without a route try every backend in the order
+/// they were given. An event none of them takes is kept by the ones that were
+/// unavailable, and a release always goes to the backends its press went to.
+pub struct CompositeEmulator {
+    backends: Vec<Box<dyn Emulator + Send + Sync>>,
+    routes: HashMap<EventKind, Vec<usize>>,
+    pressed: std::sync::Mutex<HashMap<Press, Vec<usize>>>,
+    status: Arc<watch::Sender<EmulatorStatus>>,
+}
+
+impl CompositeEmulator {
+    pub fn new(backends: Vec<Box<dyn Emulator + Send + Sync>>) -> Self {
+        let statuses: Arc<Vec<_>> = Arc::new(backends.iter().map(|b| b.status()).collect());
+        let status = Arc::new(watch::channel(combined_status(&statuses)).0);
+        for mut changes in statuses.iter().cloned() {
+            let (statuses, status) = (statuses.clone(), Arc::downgrade(&status));
+            Handle::current().spawn(async move {
+                while changes.changed().await.is_ok() {
+                    let Some(status) = status.upgrade() else {
+                        break;
+                    };
+                    let new = combined_status(&statuses);
+                    status.send_if_modified(|old| std::mem::replace(old, new.clone()) != new);
+                }
+            });
+        }
+        Self {
+            backends,
+            routes: HashMap::new(),
+            pressed: std::sync::Mutex::new(HashMap::new()),
+            status,
+        }
+    }
+
+    /// Sends `kind` to the backends at `order`, indices into the list given to `new`.
+    /// Panics on an index out of range
+    pub fn route(&mut self, kind: EventKind, order: &[usize]) {
+        assert!(
+            order.iter().all(|&i| i < self.backends.len()),
+            "route to a missing backend"
+        );
+        self.routes.insert(kind, order.to_vec());
+    }
+
+    fn order(&self, kind: EventKind) -> Vec<usize> {
+        match self.routes.get(&kind) {
+            Some(order) => order.clone(),
+            None => (0..self.backends.len()).collect(),
+        }
+    }
+
+    /// Tries the backends for `kind` until one takes the event, returning which one did
+    fn dispatch(
+        &self,
+        kind: EventKind,
+        mut f: impl FnMut(usize, &dyn Emulator) -> Result<(), EmulatorError>,
+    ) -> Result<usize, EmulatorError> {
+        let mut last = EmulatorError::Unavailable;
+        for i in self.order(kind) {
+            match f(i, self.backends[i].as_ref()) {
+                Ok(()) => return Ok(i),
+                Err(e @ (EmulatorError::Unavailable | EmulatorError::Unsupported(_))) => last = e,
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last)
+    }
+}
+
+impl Emulator for CompositeEmulator {
+    fn emulate_input(&self, hid_event: &HidEvent) -> Result<(), EmulatorError> {
+        let press = Press::of(hid_event);
+        // Autorepeat and the release go wherever the press went
+        let owners = press.and_then(|(press, dir)| {
+            let mut pressed = self.pressed.lock().unwrap();
+            match dir {
+                ElementState::Pressed => pressed.get(&press).cloned(),
+                ElementState::Released => pressed.remove(&press),
+            }
+        });
+        if let Some(owners) = owners {
+            let mut res = Err(EmulatorError::Unavailable);
+            for i in owners {
+                let sent = self.backends[i].emulate_input(hid_event);
+                if res.is_err() {
+                    res = sent;
+                }
+            }
+            return res;
+        }
+
+        let mut missed = Vec::new();
+        let res = self.dispatch(EventKind::of(hid_event), |i, emulator| {
+            let res = emulator.try_input(hid_event);
+            if res == Err(EmulatorError::Unavailable) {
+                missed.push(i);
+            }
+            res
+        });
+        let owners = match res {
+            Ok(i) => vec![i],
+            // Each keeps it and replays it if it turns out to be one it takes
+            Err(EmulatorError::Unavailable | EmulatorError::Unsupported(_)) => {
+                for &i in &missed {
+                    self.backends[i].defer(hid_event);
+                }
+                missed
+            }
+            Err(_) => Vec::new(),
+        };
+        if let Some((press, ElementState::Pressed)) = press
+            && !owners.is_empty()
+        {
+            self.pressed.lock().unwrap().insert(press, owners);
+        }
+        res.map(|_| ())
+    }
+
+    fn emulate_text(&self, text: &str) -> Result<(), EmulatorError> {
+        self.dispatch(EventKind::Text, |_, emulator| emulator.emulate_text(text))
+            .map(|_| ())
+    }
+
+    /// Keys of the backend text prefers, which then go wherever keys go
+    fn text_keys(&self, text: &str) -> Option<Result<Vec<HidEvent>, EmulatorError>> {
+        let first = *self.order(EventKind::Text).first()?;
+        self.backends[first].text_keys(text)
+    }
+
+    fn status(&self) -> watch::Receiver<EmulatorStatus> {
+        self.status.subscribe()
+    }
+
+    /// Lock state of the first backend that knows it
+    fn locks(&self) -> Option<watch::Receiver<LockState>> {
+        self.backends.iter().find_map(|emulator| emulator.locks())
+    }
+}
+
+/// Events kept while the dongle is unavailable before the oldest are folded away
+const MAX_PENDING: usize = 256;
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Fallback when hotplug notifications are available
+const HOTPLUG_POLL_INTERVAL: Duration = Duration::from_secs(10);
+/// How often the dongle is checked for LED reports
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
 #[repr(u8)]
 enum HidType {
     Key = 0,
     Mouse = 1,
     MouseButtons = 2,
     MouseScroll = 3,
+    Touch = 4,
+    Pen = 5,
+    Gamepad = 6,
+    MouseAbsolute = 7,
+    Consumer = 8,
+    /// Sent by the dongle as `[Leds, led bits]` whenever the target sets its keyboard LEDs
+    Leds = 9,
 }
-async fn get_device(
+/// The USB device and interface a [`HidEmulator`] writes to
+#[derive(Debug, Clone, Copy)]
+struct DeviceConfig {
     vid: u16,
     pid: u16,
     i_num: i32,
-    searching: Arc<AtomicBool>,
-    dev: Arc<Mutex<Option<HidDevice>>>,
+}
+
+async fn get_device<T: HidTransport>(
+    config: DeviceConfig,
+    status: Arc<watch::Sender<EmulatorStatus>>,
+    transport: Arc<Mutex<T>>,
+    dev: Arc<Mutex<Option<T::Device>>>,
+    caps: Option<Arc<std::sync::Mutex<Capabilities>>>,
+    locks: Weak<watch::Sender<LockState>>,
 ) {
-    let mut api = HidApi::new().unwrap();
+    let hotplug = transport.lock().await.hotplug();
     loop {
-        // Stop this task if the HidEmulator that spawned this task is dropped.
-        // A count of 1 signals that the HidEmulator is dropped as searching is only
-        // cloned once when spawning this task
-        if Arc::strong_count(&searching) == 1 {
-            break;
-        }
-        api.refresh_devices().unwrap();
-        match api.device_list().find(|dev| {
-            dev.vendor_id() == vid && dev.product_id() == pid && dev.interface_number() == i_num
-        }) {
-            Some(new_dev) => {
-                if let Ok(open_dev) = new_dev.open_device(&api) {
-                    let mut dev = dev.lock().await;
-                    println!("connected to device");
-                    *dev = Some(open_dev);
-                    searching.store(false, std::sync::atomic::Ordering::Release);
-                    break;
-                } else {
-                    sleep(Duration::from_secs(1)).await;
+        let opened = transport
+            .lock()
+            .await
+            .open(config.vid, config.pid, config.i_num);
+        match opened {
+            Ok(Some(open_dev)) => {
+                let mut led_id = Some(Some(HidType::Leds as u8));
+                // Stock boards only take the reports their descriptor declares
+                if let Some(caps) = &caps {
+                    let found = open_dev
+                        .report_descriptor()
+                        .map(|descriptor| Capabilities::parse(&descriptor))
+                        .unwrap_or_default();
+                    println!("device supports {:?}", found);
+                    if found.keyboard.is_none() && found.mouse.is_none() {
+                        println!("device declares neither a keyboard nor a mouse");
+                    }
+                    // Stock boards that forward the target's LED output report send it
+                    // back the way the keyboard's reports are numbered
+                    led_id = found
+                        .keyboard
+                        .map(|id| (id != report::NO_REPORT_ID).then_some(id));
+                    *caps.lock().unwrap() = found;
+                }
+                // Reports are read through a handle of their own so polling never holds
+                // up a write
+                if let Some(led_id) = led_id {
+                    match transport
+                        .lock()
+                        .await
+                        .open(config.vid, config.pid, config.i_num)
+                    {
+                        Ok(Some(reader)) => {
+                            Handle::current().spawn(read_locks(reader, led_id, locks.clone()));
+                        }
+                        _ => println!("Can't read lock state from the device"),
+                    }
                 }
+                let mut dev = dev.lock().await;
+                println!("connected to device");
+                *dev = Some(open_dev);
+                status.send_replace(EmulatorStatus::Connected);
+                break;
+            }
+            Ok(None) => wait_for_device(hotplug.as_deref()).await,
+            Err(e) => {
+                println!("Failed to look for device: {}", e);
+                status.send_replace(EmulatorStatus::Error(e.to_string()));
+                wait_for_device(hotplug.as_deref()).await;
             }
-            None => sleep(Duration::from_secs(1)).await,
         }
     }
 }
 
-pub struct HidEmulator {
-    dev: Arc<Mutex<Option<HidDevice>>>,
-    searching: Arc<AtomicBool>,
-    vid: u16,
-    pid: u16,
-    i_num: i32,
+/// Follows the LED reports, `[led_id, leds]` or just `[leds]` without an id, until the
+/// device goes away or the emulator is dropped
+async fn read_locks<D: HidWrite>(
+    reader: D,
+    led_id: Option<u8>,
+    locks: Weak<watch::Sender<LockState>>,
+) {
+    let mut buf = [0u8; 64];
+    loop {
+        sleep(LOCK_POLL_INTERVAL).await;
+        let Some(locks) = locks.upgrade() else {
+            return;
+        };
+        loop {
+            let leds = match (reader.read(&mut buf), led_id) {
+                (Ok(0), _) => break,
+                (Ok(len), Some(id)) if len >= 2 && buf[0] == id => buf[1],
+                (Ok(_), None) => buf[0],
+                (Ok(_), _) => continue,
+                (Err(_), _) => return,
+            };
+            locks.send_if_modified(|state| {
+                let new = LockState::from_hid_leds(leds);
+                std::mem::replace(state, new) != new
+            });
+        }
+    }
 }
 
-impl HidEmulator {
-    fn write_spawn(&self, dev: &HidDevice, buf: &[u8]) {
-        if dev.write(buf).is_err() {
-            println!("Disconnected device");
-            self.searching
-                .store(true, std::sync::atomic::Ordering::Release);
-            Handle::current().spawn(get_device(
-                self.vid,
-                self.pid,
-                self.i_num,
-                self.searching.clone(),
-                self.dev.clone(),
-            ));
+/// Waits for a hotplug notification, polling now and then in case one is missed
+async fn wait_for_device(hotplug: Option<&Notify>) {
+    match hotplug {
+        Some(hotplug) => {
+            select! {
+                _ = hotplug.notified() => {}
+                _ = sleep(HOTPLUG_POLL_INTERVAL) => {}
+            }
         }
+        None => sleep(POLL_INTERVAL).await,
     }
+}
 
-    pub fn new(vid: u16, pid: u16, i_num: i32) -> Self {
-        let emu = HidEmulator {
-            dev: Arc::new(Mutex::new(None)),
-            searching: Arc::new(AtomicBool::new(true)),
-            vid,
-            pid,
-            i_num,
-        };
-        Handle::current().spawn(get_device(
-            vid,
-            pid,
-            i_num,
-            emu.searching.clone(),
-            emu.dev.clone(),
-        ));
-        emu
+pub struct HidEmulator<T: HidTransport = HidApiTransport> {
+    dev: Arc<Mutex<Option<T::Device>>>,
+    transport: Arc<Mutex<T>>,
+    status: Arc<watch::Sender<EmulatorStatus>>,
+    // Only the emulator holds on to it, readers stop once it's gone
+    locks: Arc<watch::Sender<LockState>>,
+    config: DeviceConfig,
+    mode: ReportMode,
+    // Optional reports the connected device supports, outside of `ReportMode::Custom`
+    caps: Arc<std::sync::Mutex<Capabilities>>,
+    keyboard: std::sync::Mutex<KeyboardReport>,
+    mouse: std::sync::Mutex<MouseReport>,
+    consumer: std::sync::Mutex<ConsumerReport>,
+    // What the target was last told is held, and what arrived while it couldn't be told
+    held: std::sync::Mutex<InputState>,
+    pending: std::sync::Mutex<PendingQueue>,
+    // The task looking for the device, stopped when the emulator is dropped
+    search: std::sync::Mutex<Option<JoinHandle<()>>>,
+}
+
+impl<T: HidTransport> Drop for HidEmulator<T> {
+    fn drop(&mut self) {
+        if let Some(search) = self.search.lock().unwrap().take() {
+            search.abort();
+        }
     }
 }
 
-impl Emulator for HidEmulator {
-    fn emulate_input(&self, hid_event: &HidEvent) {
-        if self.searching.load(std::sync::atomic::Ordering::Acquire) {
+impl<T: HidTransport> HidEmulator<T> {
+    fn write_spawn(&self, dev: &T::Device, buf: &[u8]) -> Result<(), EmulatorError> {
+        if dev.write(buf).is_err() {
+            println!("Disconnected device");
+            // Whatever was held is gone once the device resets
+            self.keyboard.lock().unwrap().clear();
+            self.mouse.lock().unwrap().clear();
+            self.consumer.lock().unwrap().clear();
+            self.status.send_replace(EmulatorStatus::Searching);
+            self.spawn_search();
+            return Err(EmulatorError::Unavailable);
+        }
+        Ok(())
+    }
+
+    /// Starts looking for the device unless a search is already running
+    fn spawn_search(&self) {
+        let mut search = self.search.lock().unwrap();
+        if search.as_ref().is_some_and(|task| !task.is_finished()) {
             return;
         }
+        *search = Some(Handle::current().spawn(get_device(
+            self.config,
+            self.status.clone(),
+            self.transport.clone(),
+            self.dev.clone(),
+            self.negotiation(),
+            Arc::downgrade(&self.locks),
+        )));
+    }
 
-        let dev = match self.dev.try_lock() {
-            Ok(dev) => dev,
-            Err(_) => return,
-        };
-        if let Some(dev) = dev.as_ref() {
-            match hid_event {
-                HidEvent::Key(scan_code) => {
-                    if let Some(code) = scan_code.to_hid() {
-                        let mut buf = [0u8, HidType::Key as u8, code as u8, 0];
-                        buf[1] = HidType::Key as u8;
-                        buf[2] = code as u8;
-                        match scan_code.dir {
-                            ElementState::Pressed => buf[3] = 1,
-                            ElementState::Released => buf[3] = 0,
-                        }
-                        self.write_spawn(dev, &buf);
+    fn write_key(
+        &self,
+        dev: &T::Device,
+        code: HidCodes,
+        dir: ElementState,
+    ) -> Result<(), EmulatorError> {
+        let pressed = dir == ElementState::Pressed;
+        if self.mode != ReportMode::Custom {
+            let caps = *self.caps.lock().unwrap();
+            let mut keyboard = self.keyboard.lock().unwrap();
+            if keyboard.set(code, pressed) {
+                let buf = if self.mode == ReportMode::Nkro && caps.nkro {
+                    keyboard.nkro_report().to_vec()
+                } else {
+                    let id = caps.keyboard.unwrap_or(report::NO_REPORT_ID);
+                    keyboard.report(id).to_vec()
+                };
+                drop(keyboard);
+                return self.write_spawn(dev, &buf);
+            }
+            return Ok(());
+        }
+        let buf = [0u8, HidType::Key as u8, code as u8, pressed as u8];
+        self.write_spawn(dev, &buf)
+    }
+
+    fn write_consumer(
+        &self,
+        dev: &T::Device,
+        usage: u16,
+        dir: ElementState,
+    ) -> Result<(), EmulatorError> {
+        let pressed = dir == ElementState::Pressed;
+        if self.mode == ReportMode::Custom {
+            let [lo, hi] = usage.to_le_bytes();
+            let buf = [0u8, HidType::Consumer as u8, lo, hi, pressed as u8];
+            return self.write_spawn(dev, &buf);
+        }
+        let mut consumer = self.consumer.lock().unwrap();
+        if consumer.set(usage, pressed) {
+            let buf = consumer.report();
+            drop(consumer);
+            return self.write_spawn(dev, &buf);
+        }
+        Ok(())
+    }
+
+    /// Writing a report the device doesn't declare fails, which would look like an unplug
+    fn has_consumer(&self) -> bool {
+        self.mode == ReportMode::Custom || self.caps.lock().unwrap().consumer
+    }
+
+    /// Boot devices put the keyboard and the mouse on interfaces of their own, so either
+    /// may be missing from the one this emulator writes to
+    fn check_keyboard(&self) -> Result<(), EmulatorError> {
+        let caps = *self.caps.lock().unwrap();
+        if self.mode == ReportMode::Custom
+            || caps.keyboard.is_some()
+            || (self.mode == ReportMode::Nkro && caps.nkro)
+        {
+            return Ok(());
+        }
+        Err(EmulatorError::Unsupported(format!(
+            "interface {} has no keyboard",
+            self.config.i_num
+        )))
+    }
+
+    fn mouse_id(&self) -> Result<u8, EmulatorError> {
+        self.caps.lock().unwrap().mouse.ok_or_else(|| {
+            EmulatorError::Unsupported(format!("interface {} has no mouse", self.config.i_num))
+        })
+    }
+
+    fn write_mouse(&self, dev: &T::Device, dx: i8, dy: i8, wheel: i8) -> Result<(), EmulatorError> {
+        let id = self.mouse_id()?;
+        let buf = self.mouse.lock().unwrap().report(id, dx, dy, wheel);
+        self.write_spawn(dev, &buf)
+    }
+
+    fn emulate_boot(&self, dev: &T::Device, hid_event: &HidEvent) -> Result<(), EmulatorError> {
+        if let HidEvent::MouseDelta(..) | HidEvent::MouseButton(_) | HidEvent::MouseScroll(_) =
+            hid_event
+        {
+            self.mouse_id()?;
+        }
+        match hid_event {
+            HidEvent::Key(scan_code) => {
+                if let Some(usage) = scan_code.to_consumer().filter(|_| self.has_consumer()) {
+                    self.write_consumer(dev, usage, scan_code.dir)?;
+                } else if let Some(code) = scan_code.to_hid() {
+                    self.check_keyboard()?;
+                    self.write_key(dev, code, scan_code.dir)?;
+                }
+            }
+            HidEvent::MouseDelta(x, y) => {
+                for (dx, dy) in report::split_delta(*x, *y) {
+                    self.write_mouse(dev, dx, dy, 0)?;
+                }
+            }
+            HidEvent::MouseButton(mouse_buttons) => {
+                let pressed = mouse_buttons.dir == ElementState::Pressed;
+                if let Some(code) = mouse_buttons.to_hid()
+                    && self.mouse.lock().unwrap().set(code, pressed)
+                {
+                    self.write_mouse(dev, 0, 0, 0)?;
+                }
+            }
+            HidEvent::MouseScroll(offset) => {
+                for (_, wheel) in report::split_delta(0, *offset) {
+                    self.write_mouse(dev, 0, 0, wheel)?;
+                }
+            }
+            // Boot protocol only covers keyboards and relative mice
+            HidEvent::MouseAbsolute { .. }
+            | HidEvent::Touch(_)
+            | HidEvent::Pen(_)
+            | HidEvent::Gamepad(_) => {
+                return Err(EmulatorError::Unsupported(
+                    "boot protocol has no such report".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn write_event(&self, dev: &T::Device, hid_event: &HidEvent) -> Result<(), EmulatorError> {
+        if self.mode != ReportMode::Custom {
+            self.emulate_boot(dev, hid_event)?;
+            self.held.lock().unwrap().apply(hid_event);
+            return Ok(());
+        }
+        match hid_event {
+            HidEvent::Key(scan_code) => {
+                if let Some(usage) = scan_code.to_consumer() {
+                    self.write_consumer(dev, usage, scan_code.dir)?;
+                } else if let Some(code) = scan_code.to_hid() {
+                    let mut buf = [0u8, HidType::Key as u8, code as u8, 0];
+                    buf[1] = HidType::Key as u8;
+                    buf[2] = code as u8;
+                    match scan_code.dir {
+                        ElementState::Pressed => buf[3] = 1,
+                        ElementState::Released => buf[3] = 0,
                     }
+                    self.write_spawn(dev, &buf)?;
                 }
-                HidEvent::MouseDelta(x, y) => {
-                    let buf = [0, HidType::Mouse as u8, *x as u8, *y as u8];
-                    self.write_spawn(dev, &buf);
+            }
+            HidEvent::MouseDelta(x, y) => {
+                for (dx, dy) in report::split_delta(*x, *y) {
+                    let buf = [0, HidType::Mouse as u8, dx as u8, dy as u8];
+                    self.write_spawn(dev, &buf)?;
                 }
-                HidEvent::MouseButton(mouse_buttons) => {
+            }
+            HidEvent::MouseButton(mouse_buttons) => {
+                if let Some(code) = mouse_buttons.to_hid() {
                     let mut buf = [0u8; 4];
                     buf[1] = HidType::MouseButtons as u8;
-                    buf[2] = mouse_buttons.to_hid() as u8;
+                    buf[2] = code as u8;
                     match mouse_buttons.dir {
                         ElementState::Pressed => buf[3] = 1,
                         ElementState::Released => buf[3] = 0,
                     }
-                    self.write_spawn(dev, &buf);
+                    self.write_spawn(dev, &buf)?;
+                }
+            }
+            HidEvent::MouseScroll(offset) => {
+                for (_, wheel) in report::split_delta(0, *offset) {
+                    let buf = [0u8, HidType::MouseScroll as u8, wheel as u8];
+                    self.write_spawn(dev, &buf)?;
+                }
+            }
+            HidEvent::MouseAbsolute { x, y } => {
+                let [x_lo, x_hi] = x.to_le_bytes();
+                let [y_lo, y_hi] = y.to_le_bytes();
+                let buf = [0u8, HidType::MouseAbsolute as u8, x_lo, x_hi, y_lo, y_hi];
+                self.write_spawn(dev, &buf)?;
+            }
+            HidEvent::Touch(contact) => {
+                let [x_lo, x_hi] = contact.x.to_le_bytes();
+                let [y_lo, y_hi] = contact.y.to_le_bytes();
+                let buf = [
+                    0u8,
+                    HidType::Touch as u8,
+                    contact.id,
+                    contact.touching as u8,
+                    x_lo,
+                    x_hi,
+                    y_lo,
+                    y_hi,
+                ];
+                self.write_spawn(dev, &buf)?;
+            }
+            HidEvent::Pen(pen) => {
+                let [x_lo, x_hi] = pen.x.to_le_bytes();
+                let [y_lo, y_hi] = pen.y.to_le_bytes();
+                let [p_lo, p_hi] = pen.pressure.to_le_bytes();
+                let buf = [
+                    0u8,
+                    HidType::Pen as u8,
+                    (pen.tip as u8) | (pen.in_range as u8) << 1,
+                    x_lo,
+                    x_hi,
+                    y_lo,
+                    y_hi,
+                    p_lo,
+                    p_hi,
+                    pen.tilt_x as u8,
+                    pen.tilt_y as u8,
+                ];
+                self.write_spawn(dev, &buf)?;
+            }
+            HidEvent::Gamepad(pad) => {
+                let mut buf = vec![0u8, HidType::Gamepad as u8, pad.id];
+                buf.extend_from_slice(&pad.buttons.to_le_bytes());
+                for axis in [
+                    pad.left_stick.0,
+                    pad.left_stick.1,
+                    pad.right_stick.0,
+                    pad.right_stick.1,
+                ] {
+                    buf.extend_from_slice(&axis.to_le_bytes());
+                }
+                buf.extend_from_slice(&[pad.left_trigger, pad.right_trigger]);
+                self.write_spawn(dev, &buf)?;
+            }
+        }
+        self.held.lock().unwrap().apply(hid_event);
+        Ok(())
+    }
+
+    /// Brings the target to the state the client ended up in while the device was
+    /// unavailable. Whatever may still be held is released first, since a device that
+    /// only failed a `try_lock` never lost its state
+    fn flush(&self, dev: &T::Device) -> Result<(), EmulatorError> {
+        let mut pending = self.pending.lock().unwrap();
+        if pending.is_empty() {
+            return Ok(());
+        }
+        // Events were queued before it was known which this interface takes
+        let write = |event: &HidEvent| match self.write_event(dev, event) {
+            Err(EmulatorError::Unsupported(_)) => Ok(()),
+            res => res,
+        };
+        let held = self.held.lock().unwrap().clone();
+        for event in held.events(ElementState::Released) {
+            write(&event)?;
+        }
+        for event in pending.desired().events(ElementState::Pressed) {
+            write(&event)?;
+        }
+        for event in pending.replay() {
+            write(event)?;
+        }
+        pending.clear();
+        Ok(())
+    }
+
+    fn with_device<R>(
+        &self,
+        f: impl FnOnce(&T::Device) -> Result<R, EmulatorError>,
+    ) -> Result<R, EmulatorError> {
+        if self.is_searching() {
+            return Err(EmulatorError::Unavailable);
+        }
+        let dev = self
+            .dev
+            .try_lock()
+            .map_err(|_| EmulatorError::Unavailable)?;
+        f(dev.as_ref().ok_or(EmulatorError::Unavailable)?)
+    }
+
+    /// Same as [`HidEmulator::with_mode`] but finds the device through `transport`
+    pub fn with_transport(transport: T, vid: u16, pid: u16, i_num: i32, mode: ReportMode) -> Self {
+        let emu = HidEmulator {
+            dev: Arc::new(Mutex::new(None)),
+            transport: Arc::new(Mutex::new(transport)),
+            status: Arc::new(watch::channel(EmulatorStatus::Searching).0),
+            locks: Arc::new(watch::channel(LockState::default()).0),
+            config: DeviceConfig { vid, pid, i_num },
+            mode,
+            caps: Arc::new(std::sync::Mutex::new(Capabilities::default())),
+            keyboard: std::sync::Mutex::new(KeyboardReport::new()),
+            mouse: std::sync::Mutex::new(MouseReport::new()),
+            consumer: std::sync::Mutex::new(ConsumerReport::new()),
+            held: std::sync::Mutex::new(InputState::new()),
+            pending: std::sync::Mutex::new(PendingQueue::new(MAX_PENDING)),
+            search: std::sync::Mutex::new(None),
+        };
+        emu.spawn_search();
+        emu
+    }
+
+    fn negotiation(&self) -> Option<Arc<std::sync::Mutex<Capabilities>>> {
+        (self.mode != ReportMode::Custom).then(|| self.caps.clone())
+    }
+
+    /// True while the device is missing and a task is looking for it
+    pub fn is_searching(&self) -> bool {
+        *self.status.borrow() == EmulatorStatus::Searching
+    }
+}
+
+impl HidEmulator {
+    pub fn new(vid: u16, pid: u16, i_num: i32) -> Self {
+        Self::with_mode(vid, pid, i_num, ReportMode::Custom)
+    }
+
+    pub fn with_mode(vid: u16, pid: u16, i_num: i32, mode: ReportMode) -> Self {
+        Self::with_transport(HidApiTransport::new(), vid, pid, i_num, mode)
+    }
+}
+
+impl<T: HidTransport> Emulator for HidEmulator<T> {
+    fn emulate_input(&self, hid_event: &HidEvent) -> Result<(), EmulatorError> {
+        let res = self.try_input(hid_event);
+        if res == Err(EmulatorError::Unavailable) {
+            self.defer(hid_event);
+        }
+        res
+    }
+
+    fn try_input(&self, hid_event: &HidEvent) -> Result<(), EmulatorError> {
+        self.with_device(|dev| {
+            self.flush(dev)?;
+            self.write_event(dev, hid_event)
+        })
+    }
+
+    fn defer(&self, hid_event: &HidEvent) {
+        let held = self.held.lock().unwrap();
+        self.pending.lock().unwrap().push(*hid_event, &held);
+    }
+
+    // The dongle only knows key presses, so text is typed as if the target used a US layout.
+    // Text with anything else in it isn't typed at all, rather than typed with holes
+    fn emulate_text(&self, text: &str) -> Result<(), EmulatorError> {
+        let keys = text
+            .chars()
+            .map(|c| {
+                char_to_hid(c).ok_or_else(|| {
+                    EmulatorError::Unsupported(format!("no US layout key types {:?}", c))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.with_device(|dev| {
+            self.check_keyboard()?;
+            self.flush(dev)?;
+            for (code, shift) in keys {
+                if shift {
+                    self.write_key(dev, HidCodes::KeyboardLeftShift, ElementState::Pressed)?;
                 }
-                HidEvent::MouseScroll(offset) => {
-                    let buf = [0u8, HidType::MouseScroll as u8, *offset as u8];
-                    self.write_spawn(dev, &buf);
+                self.write_key(dev, code, ElementState::Pressed)?;
+                self.write_key(dev, code, ElementState::Released)?;
+                if shift {
+                    self.write_key(dev, HidCodes::KeyboardLeftShift, ElementState::Released)?;
                 }
             }
+            Ok(())
+        })
+    }
+
+    fn text_keys(&self, text: &str) -> Option<Result<Vec<HidEvent>, EmulatorError>> {
+        Some(us_layout_keys(text))
+    }
+
+    fn status(&self) -> watch::Receiver<EmulatorStatus> {
+        self.status.subscribe()
+    }
+
+    fn locks(&self) -> Option<watch::Receiver<LockState>> {
+        Some(self.locks.subscribe())
+    }
+}
+
+/// Software backend for Linux servers without the USB dongle. Input is injected through
+/// virtual devices created on `/dev/uinput`, so it works under X11, Wayland and the console
+#[cfg(target_os = "linux")]
+pub struct UinputEmulator {
+    keyboard: std::sync::Mutex<VirtualDevice>,
+    mouse: std::sync::Mutex<VirtualDevice>,
+    // Absolute positioning needs its own device, one with both relative and absolute
+    // axes isn't treated as a pointer by libinput
+    tablet: std::sync::Mutex<VirtualDevice>,
+    touchscreen: std::sync::Mutex<Touchscreen>,
+    pen: std::sync::Mutex<VirtualDevice>,
+}
+
+/// Contact slots of the virtual touchscreen, as many as the client tracks
+#[cfg(target_os = "linux")]
+const TOUCH_SLOTS: usize = 10;
+
+#[cfg(target_os = "linux")]
+struct Touchscreen {
+    device: VirtualDevice,
+    // Tracking id of the contact in each slot
+    contacts: [Option<i32>; TOUCH_SLOTS],
+    next_id: i32,
+}
+
+#[cfg(target_os = "linux")]
+impl Touchscreen {
+    fn emit(&mut self, contact: &TouchContact) -> std::io::Result<()> {
+        let slot = contact.id as usize;
+        if slot >= TOUCH_SLOTS {
+            return Err(std::io::Error::other(format!("no touch slot {}", slot)));
+        }
+        let abs = |code: AbsoluteAxisCode, value: i32| {
+            InputEvent::new(EventType::ABSOLUTE.0, code.0, value)
+        };
+        let was_touching = self.contacts.iter().any(Option::is_some);
+        let mut events = vec![abs(AbsoluteAxisCode::ABS_MT_SLOT, slot as i32)];
+        if contact.touching {
+            if self.contacts[slot].is_none() {
+                self.contacts[slot] = Some(self.next_id);
+                events.push(abs(AbsoluteAxisCode::ABS_MT_TRACKING_ID, self.next_id));
+                self.next_id = (self.next_id + 1) % u16::MAX as i32;
+            }
+            events.extend([
+                abs(AbsoluteAxisCode::ABS_MT_POSITION_X, contact.x as i32),
+                abs(AbsoluteAxisCode::ABS_MT_POSITION_Y, contact.y as i32),
+                // Single touch axes for clients that don't read the slots
+                abs(AbsoluteAxisCode::ABS_X, contact.x as i32),
+                abs(AbsoluteAxisCode::ABS_Y, contact.y as i32),
+            ]);
+        } else if self.contacts[slot].take().is_some() {
+            events.push(abs(AbsoluteAxisCode::ABS_MT_TRACKING_ID, -1));
+        } else {
+            return Ok(());
+        }
+        let touching = self.contacts.iter().any(Option::is_some);
+        if touching != was_touching {
+            events.push(InputEvent::new(
+                EventType::KEY.0,
+                EvdevKey::BTN_TOUCH.0,
+                touching as i32,
+            ));
+        }
+        self.device.emit(&events)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl UinputEmulator {
+    pub fn new() -> std::io::Result<Self> {
+        // Everything below BTN_0 is a keyboard key, which together with Fn covers all of
+        // `to_evdev`
+        let mut keys = AttributeSet::<EvdevKey>::new();
+        for code in EvdevKey::KEY_ESC.code()..EvdevKey::BTN_0.code() {
+            keys.insert(EvdevKey::new(code));
+        }
+        keys.insert(EvdevKey::KEY_FN);
+        let keyboard = VirtualDevice::builder()?
+            .name("Streamer Keyboard")
+            .with_keys(&keys)?
+            .build()?;
+
+        let buttons = AttributeSet::from_iter([
+            EvdevKey::BTN_LEFT,
+            EvdevKey::BTN_RIGHT,
+            EvdevKey::BTN_MIDDLE,
+            EvdevKey::BTN_SIDE,
+            EvdevKey::BTN_EXTRA,
+        ]);
+        let axes = AttributeSet::from_iter([
+            RelativeAxisCode::REL_X,
+            RelativeAxisCode::REL_Y,
+            RelativeAxisCode::REL_WHEEL,
+            RelativeAxisCode::REL_WHEEL_HI_RES,
+        ]);
+        let mouse = VirtualDevice::builder()?
+            .name("Streamer Mouse")
+            .with_keys(&buttons)?
+            .with_relative_axes(&axes)?
+            .build()?;
+
+        let position = AbsInfo::new(0, 0, u16::MAX as i32, 0, 0, 0);
+        let tablet = VirtualDevice::builder()?
+            .name("Streamer Absolute Pointer")
+            .with_keys(&AttributeSet::from_iter([EvdevKey::BTN_LEFT]))?
+            .with_absolute_axis(&UinputAbsSetup::new(AbsoluteAxisCode::ABS_X, position))?
+            .with_absolute_axis(&UinputAbsSetup::new(AbsoluteAxisCode::ABS_Y, position))?
+            .build()?;
+
+        let direct = AttributeSet::from_iter([PropType::DIRECT]);
+        let slots = AbsInfo::new(0, 0, TOUCH_SLOTS as i32 - 1, 0, 0, 0);
+        let tracking = AbsInfo::new(0, 0, u16::MAX as i32, 0, 0, 0);
+        let touchscreen = VirtualDevice::builder()?
+            .name("Streamer Touchscreen")
+            .with_properties(&direct)?
+            .with_keys(&AttributeSet::from_iter([EvdevKey::BTN_TOUCH]))?
+            .with_absolute_axis(&UinputAbsSetup::new(AbsoluteAxisCode::ABS_X, position))?
+            .with_absolute_axis(&UinputAbsSetup::new(AbsoluteAxisCode::ABS_Y, position))?
+            .with_absolute_axis(&UinputAbsSetup::new(AbsoluteAxisCode::ABS_MT_SLOT, slots))?
+            .with_absolute_axis(&UinputAbsSetup::new(
+                AbsoluteAxisCode::ABS_MT_TRACKING_ID,
+                tracking,
+            ))?
+            .with_absolute_axis(&UinputAbsSetup::new(
+                AbsoluteAxisCode::ABS_MT_POSITION_X,
+                position,
+            ))?
+            .with_absolute_axis(&UinputAbsSetup::new(
+                AbsoluteAxisCode::ABS_MT_POSITION_Y,
+                position,
+            ))?
+            .build()?;
+
+        let pressure = AbsInfo::new(0, 0, u16::MAX as i32, 0, 0, 0);
+        // Tilt is in degrees, which is a resolution of 57 units per radian
+        let tilt = AbsInfo::new(0, -90, 90, 0, 0, 57);
+        let pen = VirtualDevice::builder()?
+            .name("Streamer Pen")
+            .with_properties(&direct)?
+            .with_keys(&AttributeSet::from_iter([
+                EvdevKey::BTN_TOOL_PEN,
+                EvdevKey::BTN_TOUCH,
+            ]))?
+            .with_absolute_axis(&UinputAbsSetup::new(AbsoluteAxisCode::ABS_X, position))?
+            .with_absolute_axis(&UinputAbsSetup::new(AbsoluteAxisCode::ABS_Y, position))?
+            .with_absolute_axis(&UinputAbsSetup::new(
+                AbsoluteAxisCode::ABS_PRESSURE,
+                pressure,
+            ))?
+            .with_absolute_axis(&UinputAbsSetup::new(AbsoluteAxisCode::ABS_TILT_X, tilt))?
+            .with_absolute_axis(&UinputAbsSetup::new(AbsoluteAxisCode::ABS_TILT_Y, tilt))?
+            .build()?;
+
+        Ok(Self {
+            keyboard: std::sync::Mutex::new(keyboard),
+            mouse: std::sync::Mutex::new(mouse),
+            tablet: std::sync::Mutex::new(tablet),
+            touchscreen: std::sync::Mutex::new(Touchscreen {
+                device: touchscreen,
+                contacts: [None; TOUCH_SLOTS],
+                next_id: 0,
+            }),
+            pen: std::sync::Mutex::new(pen),
+        })
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Emulator for UinputEmulator {
+    fn emulate_input(&self, hid_event: &HidEvent) -> Result<(), EmulatorError> {
+        let key = |code: EvdevKey, dir: ElementState| {
+            InputEvent::new(
+                EventType::KEY.0,
+                code.0,
+                (dir == ElementState::Pressed) as i32,
+            )
+        };
+        let rel = |code: RelativeAxisCode, value: i32| {
+            InputEvent::new(EventType::RELATIVE.0, code.0, value)
+        };
+        let abs = |code: AbsoluteAxisCode, value: i32| {
+            InputEvent::new(EventType::ABSOLUTE.0, code.0, value)
+        };
+        let pressed = |down: bool| {
+            if down {
+                ElementState::Pressed
+            } else {
+                ElementState::Released
+            }
+        };
+        match hid_event {
+            HidEvent::Key(scan_code) => match scan_code.to_evdev() {
+                Some(code) => self
+                    .keyboard
+                    .lock()
+                    .unwrap()
+                    .emit(&[key(code, scan_code.dir)]),
+                None => Ok(()),
+            },
+            HidEvent::MouseDelta(x, y) => self.mouse.lock().unwrap().emit(&[
+                rel(RelativeAxisCode::REL_X, *x),
+                rel(RelativeAxisCode::REL_Y, *y),
+            ]),
+            HidEvent::MouseButton(mouse_buttons) => match mouse_buttons.to_evdev() {
+                Some(code) => self
+                    .mouse
+                    .lock()
+                    .unwrap()
+                    .emit(&[key(code, mouse_buttons.dir)]),
+                None => Ok(()),
+            },
+            // A wheel detent is 120 units on the high resolution axis
+            HidEvent::MouseScroll(scroll) => self.mouse.lock().unwrap().emit(&[
+                rel(RelativeAxisCode::REL_WHEEL, *scroll),
+                rel(RelativeAxisCode::REL_WHEEL_HI_RES, *scroll * 120),
+            ]),
+            HidEvent::MouseAbsolute { x, y } => self.tablet.lock().unwrap().emit(&[
+                abs(AbsoluteAxisCode::ABS_X, *x as i32),
+                abs(AbsoluteAxisCode::ABS_Y, *y as i32),
+            ]),
+            HidEvent::Touch(contact) => self.touchscreen.lock().unwrap().emit(contact),
+            HidEvent::Pen(pen) => self.pen.lock().unwrap().emit(&[
+                key(EvdevKey::BTN_TOOL_PEN, pressed(pen.in_range)),
+                key(EvdevKey::BTN_TOUCH, pressed(pen.tip)),
+                abs(AbsoluteAxisCode::ABS_X, pen.x as i32),
+                abs(AbsoluteAxisCode::ABS_Y, pen.y as i32),
+                abs(AbsoluteAxisCode::ABS_PRESSURE, pen.pressure as i32),
+                abs(AbsoluteAxisCode::ABS_TILT_X, pen.tilt_x as i32),
+                abs(AbsoluteAxisCode::ABS_TILT_Y, pen.tilt_y as i32),
+            ]),
+            // Controllers get devices of their own from UinputGamepads
+            HidEvent::Gamepad(_) => {
+                return Err(EmulatorError::Unsupported(
+                    "gamepads need UinputGamepads".to_string(),
+                ));
+            }
+        }
+        .map_err(failed)
+    }
+
+    // enigo needs a display server, so text goes through the keyboard device as if the
+    // target used a US layout
+    fn emulate_text(&self, text: &str) -> Result<(), EmulatorError> {
+        for hid_event in us_layout_keys(text)? {
+            self.emulate_input(&hid_event)?;
+        }
+        Ok(())
+    }
+
+    fn text_keys(&self, text: &str) -> Option<Result<Vec<HidEvent>, EmulatorError>> {
+        Some(us_layout_keys(text))
+    }
+}
+
+/// Exposes forwarded controllers as uinput gamepads, one virtual device per controller,
+/// and hands every other event to `inner`
+#[cfg(target_os = "linux")]
+pub struct UinputGamepads<E> {
+    inner: E,
+    pads: std::sync::Mutex<HashMap<u8, VirtualDevice>>,
+}
+
+#[cfg(target_os = "linux")]
+impl<E: Emulator> UinputGamepads<E> {
+    pub fn new(inner: E) -> Self {
+        Self {
+            inner,
+            pads: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn create_pad(id: u8) -> std::io::Result<VirtualDevice> {
+        let mut keys = AttributeSet::<EvdevKey>::new();
+        for button in GamepadButton::ALL {
+            if let Some(key) = gamepad_button_to_evdev(button) {
+                keys.insert(key);
+            }
+        }
+        let stick = AbsInfo::new(0, -32767, 32767, 16, 128, 0);
+        let trigger = AbsInfo::new(0, 0, 255, 0, 0, 0);
+        let hat = AbsInfo::new(0, -1, 1, 0, 0, 0);
+        let name = format!("Streamer Gamepad {}", id);
+        VirtualDevice::builder()?
+            .name(&name)
+            // Report as an Xbox 360 pad so games and SDL pick a sensible mapping
+            .input_id(InputId::new(BusType::BUS_USB, 0x045e, 0x028e, 0x110))
+            .with_keys(&keys)?
+            .with_absolute_axis(&UinputAbsSetup::new(AbsoluteAxisCode::ABS_X, stick))?
+            .with_absolute_axis(&UinputAbsSetup::new(AbsoluteAxisCode::ABS_Y, stick))?
+            .with_absolute_axis(&UinputAbsSetup::new(AbsoluteAxisCode::ABS_RX, stick))?
+            .with_absolute_axis(&UinputAbsSetup::new(AbsoluteAxisCode::ABS_RY, stick))?
+            .with_absolute_axis(&UinputAbsSetup::new(AbsoluteAxisCode::ABS_Z, trigger))?
+            .with_absolute_axis(&UinputAbsSetup::new(AbsoluteAxisCode::ABS_RZ, trigger))?
+            .with_absolute_axis(&UinputAbsSetup::new(AbsoluteAxisCode::ABS_HAT0X, hat))?
+            .with_absolute_axis(&UinputAbsSetup::new(AbsoluteAxisCode::ABS_HAT0Y, hat))?
+            .build()
+    }
+
+    fn emulate_gamepad(&self, pad: &GamepadState) -> std::io::Result<()> {
+        let mut pads = self.pads.lock().unwrap();
+        let dev = match pads.entry(pad.id) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(Self::create_pad(pad.id)?),
+        };
+
+        let mut events = Vec::with_capacity(GamepadButton::ALL.len() + 8);
+        for button in GamepadButton::ALL {
+            if let Some(key) = gamepad_button_to_evdev(button) {
+                events.push(InputEvent::new(
+                    EventType::KEY.0,
+                    key.0,
+                    pad.is_pressed(button) as i32,
+                ));
+            }
+        }
+        let axis = |code: AbsoluteAxisCode, value: i32| {
+            InputEvent::new(EventType::ABSOLUTE.0, code.0, value)
+        };
+        let dpad = |neg: GamepadButton, pos: GamepadButton| {
+            pad.is_pressed(pos) as i32 - pad.is_pressed(neg) as i32
+        };
+        // evdev's y axes point down while the snapshot's point up
+        events.extend([
+            axis(AbsoluteAxisCode::ABS_X, pad.left_stick.0 as i32),
+            axis(AbsoluteAxisCode::ABS_Y, -(pad.left_stick.1 as i32)),
+            axis(AbsoluteAxisCode::ABS_RX, pad.right_stick.0 as i32),
+            axis(AbsoluteAxisCode::ABS_RY, -(pad.right_stick.1 as i32)),
+            axis(AbsoluteAxisCode::ABS_Z, pad.left_trigger as i32),
+            axis(AbsoluteAxisCode::ABS_RZ, pad.right_trigger as i32),
+            axis(
+                AbsoluteAxisCode::ABS_HAT0X,
+                dpad(GamepadButton::DPadLeft, GamepadButton::DPadRight),
+            ),
+            axis(
+                AbsoluteAxisCode::ABS_HAT0Y,
+                dpad(GamepadButton::DPadUp, GamepadButton::DPadDown),
+            ),
+        ]);
+        dev.emit(&events)
+    }
+}
+
+/// The d-pad is reported through the hat axes instead of buttons
+#[cfg(target_os = "linux")]
+fn gamepad_button_to_evdev(button: GamepadButton) -> Option<EvdevKey> {
+    match button {
+        GamepadButton::South => Some(EvdevKey::BTN_SOUTH),
+        GamepadButton::East => Some(EvdevKey::BTN_EAST),
+        GamepadButton::North => Some(EvdevKey::BTN_NORTH),
+        GamepadButton::West => Some(EvdevKey::BTN_WEST),
+        GamepadButton::LeftBumper => Some(EvdevKey::BTN_TL),
+        GamepadButton::RightBumper => Some(EvdevKey::BTN_TR),
+        GamepadButton::Select => Some(EvdevKey::BTN_SELECT),
+        GamepadButton::Start => Some(EvdevKey::BTN_START),
+        GamepadButton::Mode => Some(EvdevKey::BTN_MODE),
+        GamepadButton::LeftThumb => Some(EvdevKey::BTN_THUMBL),
+        GamepadButton::RightThumb => Some(EvdevKey::BTN_THUMBR),
+        GamepadButton::DPadUp
+        | GamepadButton::DPadDown
+        | GamepadButton::DPadLeft
+        | GamepadButton::DPadRight => None,
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl<E: Emulator> Emulator for UinputGamepads<E> {
+    fn emulate_input(&self, hid_event: &HidEvent) -> Result<(), EmulatorError> {
+        match hid_event {
+            HidEvent::Gamepad(pad) => self.emulate_gamepad(pad).map_err(failed),
+            _ => self.inner.emulate_input(hid_event),
+        }
+    }
+
+    fn emulate_text(&self, text: &str) -> Result<(), EmulatorError> {
+        self.inner.emulate_text(text)
+    }
+
+    fn text_keys(&self, text: &str) -> Option<Result<Vec<HidEvent>, EmulatorError>> {
+        self.inner.text_keys(text)
+    }
+
+    fn status(&self) -> watch::Receiver<EmulatorStatus> {
+        self.inner.status()
+    }
+
+    fn locks(&self) -> Option<watch::Receiver<LockState>> {
+        self.inner.locks()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::event::MouseButton;
+
+    use super::*;
+    use crate::{
+        codes::{MouseButtons, ScanCode},
+        transport::MemoryTransport,
+    };
+
+    fn key(code: KeyCode, dir: ElementState) -> HidEvent {
+        HidEvent::Key(ScanCode::new(code, dir))
+    }
+
+    async fn wait_for_status(emulator: &impl Emulator, expected: EmulatorStatus) {
+        let mut status = emulator.status();
+        tokio::time::timeout(Duration::from_secs(1), status.wait_for(|s| *s == expected))
+            .await
+            .expect("status never changed")
+            .unwrap();
+    }
+
+    /// An emulator that found the device attached to the returned transport
+    async fn connected(mode: ReportMode) -> (HidEmulator<MemoryTransport>, MemoryTransport) {
+        let transport = MemoryTransport::new();
+        transport.attach();
+        let emulator = HidEmulator::with_transport(transport.clone(), 1, 2, 3, mode);
+        wait_for_status(&emulator, EmulatorStatus::Connected).await;
+        (emulator, transport)
+    }
+
+    #[tokio::test]
+    async fn custom_reports() {
+        let (emulator, transport) = connected(ReportMode::Custom).await;
+        let left = MouseButtons::new(MouseButton::Left, ElementState::Pressed);
+        let left_code = left.to_hid().unwrap() as u8;
+        for hid_event in [
+            key(KeyCode::KeyA, ElementState::Pressed),
+            key(KeyCode::KeyA, ElementState::Released),
+            HidEvent::MouseDelta(5, -3),
+            HidEvent::MouseButton(left),
+            HidEvent::MouseScroll(-1),
+            HidEvent::MouseAbsolute {
+                x: 0x1234,
+                y: 0xabcd,
+            },
+            key(KeyCode::AudioVolumeUp, ElementState::Pressed),
+            key(KeyCode::MediaSelect, ElementState::Released),
+        ] {
+            emulator.emulate_input(&hid_event).unwrap();
+        }
+        assert_eq!(
+            transport.reports(),
+            [
+                vec![0, HidType::Key as u8, 0x04, 1],
+                vec![0, HidType::Key as u8, 0x04, 0],
+                vec![0, HidType::Mouse as u8, 5, 0xfd],
+                vec![0, HidType::MouseButtons as u8, left_code, 1],
+                vec![0, HidType::MouseScroll as u8, 0xff],
+                vec![0, HidType::MouseAbsolute as u8, 0x34, 0x12, 0xcd, 0xab],
+                vec![0, HidType::Consumer as u8, 0xe9, 0x00, 1],
+                vec![0, HidType::Consumer as u8, 0x83, 0x01, 0],
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn custom_text_types_us_layout_keys() {
+        let (emulator, transport) = connected(ReportMode::Custom).await;
+        emulator.emulate_text("a!").unwrap();
+        let shift = HidCodes::KeyboardLeftShift as u8;
+        assert_eq!(
+            transport.take_reports(),
+            [
+                vec![0, HidType::Key as u8, 0x04, 1],
+                vec![0, HidType::Key as u8, 0x04, 0],
+                vec![0, HidType::Key as u8, shift, 1],
+                vec![0, HidType::Key as u8, 0x1e, 1],
+                vec![0, HidType::Key as u8, 0x1e, 0],
+                vec![0, HidType::Key as u8, shift, 0],
+            ]
+        );
+        // Nothing at all is typed when a character has no key
+        assert!(matches!(
+            emulator.emulate_text("aé"),
+            Err(EmulatorError::Unsupported(_))
+        ));
+        assert!(transport.reports().is_empty());
+    }
+
+    #[tokio::test]
+    async fn replays_after_replug() {
+        let (emulator, transport) = connected(ReportMode::Custom).await;
+        transport.detach();
+        assert_eq!(
+            emulator.emulate_input(&key(KeyCode::KeyA, ElementState::Pressed)),
+            Err(EmulatorError::Unavailable)
+        );
+        assert!(emulator.is_searching());
+        for delta in [HidEvent::MouseDelta(3, 4), HidEvent::MouseDelta(-1, 2)] {
+            assert_eq!(
+                emulator.emulate_input(&delta),
+                Err(EmulatorError::Unavailable)
+            );
+        }
+        assert!(transport.reports().is_empty());
+
+        let opens = transport.opens();
+        transport.attach();
+        wait_for_status(&emulator, EmulatorStatus::Connected).await;
+        assert!(transport.opens() > opens);
+
+        // The key still held is pressed and the motion merged before the next event
+        emulator
+            .emulate_input(&key(KeyCode::KeyA, ElementState::Released))
+            .unwrap();
+        assert_eq!(
+            transport.reports(),
+            [
+                vec![0, HidType::Key as u8, 0x04, 1],
+                vec![0, HidType::Mouse as u8, 2, 6],
+                vec![0, HidType::Key as u8, 0x04, 0],
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn failing_writes_start_a_search() {
+        let (emulator, transport) = connected(ReportMode::Custom).await;
+        transport.set_failing(true);
+        assert_eq!(
+            emulator.emulate_input(&HidEvent::MouseScroll(1)),
+            Err(EmulatorError::Unavailable)
+        );
+        assert_eq!(*emulator.status().borrow(), EmulatorStatus::Searching);
+        // The same device enumerates again and takes writes once it recovers
+        transport.set_failing(false);
+        transport.attach();
+        wait_for_status(&emulator, EmulatorStatus::Connected).await;
+        emulator.emulate_input(&HidEvent::MouseScroll(1)).unwrap();
+        assert_eq!(
+            transport.reports(),
+            [
+                vec![0, HidType::MouseScroll as u8, 1],
+                vec![0, HidType::MouseScroll as u8, 1],
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn reads_lock_leds() {
+        let (emulator, transport) = connected(ReportMode::Custom).await;
+        let mut locks = emulator.locks().unwrap();
+        transport.send_input(vec![HidType::Leds as u8, 0b011]);
+        let locks = tokio::time::timeout(Duration::from_secs(1), locks.wait_for(|l| l.caps))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            *locks,
+            LockState {
+                caps: true,
+                num: true,
+                scroll: false,
+            }
+        );
+    }
+
+    /// A boot mode emulator on an interface with `descriptor`
+    async fn boot(descriptor: &[u8]) -> (HidEmulator<MemoryTransport>, MemoryTransport) {
+        let transport = MemoryTransport::new();
+        transport.set_descriptor(descriptor.to_vec());
+        transport.attach();
+        let emulator = HidEmulator::with_transport(transport.clone(), 1, 2, 3, ReportMode::Boot);
+        wait_for_status(&emulator, EmulatorStatus::Connected).await;
+        (emulator, transport)
+    }
+
+    #[tokio::test]
+    async fn boot_keyboard_interface() {
+        let (emulator, transport) = boot(report::tests::BOOT_KEYBOARD).await;
+        emulator
+            .emulate_input(&key(KeyCode::KeyA, ElementState::Pressed))
+            .unwrap();
+        assert!(matches!(
+            emulator.emulate_input(&HidEvent::MouseDelta(1, 1)),
+            Err(EmulatorError::Unsupported(_))
+        ));
+        assert_eq!(transport.reports(), [vec![0, 0, 0, 0x04, 0, 0, 0, 0, 0]]);
+
+        // The LED output report comes back without an id too
+        let mut locks = emulator.locks().unwrap();
+        transport.send_input(vec![0b011]);
+        let locks = tokio::time::timeout(Duration::from_secs(1), locks.wait_for(|l| l.caps))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(locks.num && !locks.scroll);
+    }
+
+    #[tokio::test]
+    async fn boot_interfaces_take_their_own_reports() {
+        let (keyboard, keyboard_transport) = boot(report::tests::BOOT_KEYBOARD).await;
+        let (mouse, mouse_transport) = boot(report::tests::BOOT_MOUSE).await;
+        let emulator = CompositeEmulator::new(vec![Box::new(keyboard), Box::new(mouse)]);
+        emulator
+            .emulate_input(&key(KeyCode::ShiftLeft, ElementState::Pressed))
+            .unwrap();
+        emulator
+            .emulate_input(&HidEvent::MouseDelta(5, -3))
+            .unwrap();
+        emulator.emulate_text("a").unwrap();
+        assert_eq!(
+            keyboard_transport.reports(),
+            [
+                vec![0, 0x02, 0, 0, 0, 0, 0, 0, 0],
+                vec![0, 0x02, 0, 0x04, 0, 0, 0, 0, 0],
+                vec![0, 0x02, 0, 0, 0, 0, 0, 0, 0],
+            ]
+        );
+        assert_eq!(mouse_transport.reports(), [vec![0, 0, 5, 0xfd, 0]]);
+    }
+
+    #[tokio::test]
+    async fn fallback_keeps_what_it_pressed() {
+        let primary_transport = MemoryTransport::new();
+        let primary =
+            HidEmulator::with_transport(primary_transport.clone(), 1, 2, 3, ReportMode::Custom);
+        let mut primary_status = primary.status();
+        let (fallback, fallback_transport) = connected(ReportMode::Custom).await;
+        let emulator = CompositeEmulator::new(vec![Box::new(primary), Box::new(fallback)]);
+        assert_eq!(*emulator.status().borrow(), EmulatorStatus::Connected);
+
+        emulator
+            .emulate_input(&key(KeyCode::KeyA, ElementState::Pressed))
+            .unwrap();
+        primary_transport.attach();
+        tokio::time::timeout(
+            Duration::from_secs(1),
+            primary_status.wait_for(|s| *s == EmulatorStatus::Connected),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        for hid_event in [
+            key(KeyCode::KeyA, ElementState::Released),
+            key(KeyCode::KeyB, ElementState::Pressed),
+        ] {
+            emulator.emulate_input(&hid_event).unwrap();
+        }
+        assert_eq!(
+            fallback_transport.reports(),
+            [
+                vec![0, HidType::Key as u8, 0x04, 1],
+                vec![0, HidType::Key as u8, 0x04, 0],
+            ]
+        );
+        // Nothing the fallback took is replayed once the primary is back
+        assert_eq!(
+            primary_transport.reports(),
+            [vec![0, HidType::Key as u8, 0x05, 1]]
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    mod uinput {
+        use evdev::Device;
+
+        use super::*;
+        use crate::codes::PenState;
+
+        fn open(device: &mut VirtualDevice) -> Device {
+            // The node shows up once udev has seen the device
+            for _ in 0..50 {
+                if let Some(Ok(path)) = device.enumerate_dev_nodes_blocking().unwrap().next() {
+                    return Device::open(path).unwrap();
+                }
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            panic!("no event node for the virtual device");
+        }
+
+        /// Events read back from the node, without the SYN_REPORTs
+        fn read_back(device: &mut Device) -> Vec<(EventType, u16, i32)> {
+            device
+                .fetch_events()
+                .unwrap()
+                .filter(|event| event.event_type() != EventType::SYNCHRONIZATION)
+                .map(|event| (event.event_type(), event.code(), event.value()))
+                .collect()
+        }
+
+        #[test]
+        #[ignore = "needs write access to /dev/uinput"]
+        fn events_read_back_from_the_nodes() {
+            let emulator = UinputEmulator::new().unwrap();
+            let mut keyboard = open(&mut emulator.keyboard.lock().unwrap());
+            let mut mouse = open(&mut emulator.mouse.lock().unwrap());
+            let mut touchscreen = open(&mut emulator.touchscreen.lock().unwrap().device);
+            let mut pen = open(&mut emulator.pen.lock().unwrap());
+
+            let key = ScanCode::new(KeyCode::KeyA, ElementState::Pressed);
+            emulator.emulate_input(&HidEvent::Key(key)).unwrap();
+            assert_eq!(
+                read_back(&mut keyboard),
+                [(EventType::KEY, EvdevKey::KEY_A.0, 1)]
+            );
+
+            emulator
+                .emulate_input(&HidEvent::MouseDelta(5, -3))
+                .unwrap();
+            assert_eq!(
+                read_back(&mut mouse),
+                [
+                    (EventType::RELATIVE, RelativeAxisCode::REL_X.0, 5),
+                    (EventType::RELATIVE, RelativeAxisCode::REL_Y.0, -3),
+                ]
+            );
+            let button = MouseButtons::new(MouseButton::Right, ElementState::Pressed);
+            emulator
+                .emulate_input(&HidEvent::MouseButton(button))
+                .unwrap();
+            assert_eq!(
+                read_back(&mut mouse),
+                [(EventType::KEY, EvdevKey::BTN_RIGHT.0, 1)]
+            );
+
+            let contact = |touching| TouchContact {
+                id: 1,
+                touching,
+                x: 1000,
+                y: 2000,
+            };
+            emulator
+                .emulate_input(&HidEvent::Touch(contact(true)))
+                .unwrap();
+            let events = read_back(&mut touchscreen);
+            for expected in [
+                (EventType::ABSOLUTE, AbsoluteAxisCode::ABS_MT_SLOT.0, 1),
+                (
+                    EventType::ABSOLUTE,
+                    AbsoluteAxisCode::ABS_MT_TRACKING_ID.0,
+                    0,
+                ),
+                (
+                    EventType::ABSOLUTE,
+                    AbsoluteAxisCode::ABS_MT_POSITION_X.0,
+                    1000,
+                ),
+                (
+                    EventType::ABSOLUTE,
+                    AbsoluteAxisCode::ABS_MT_POSITION_Y.0,
+                    2000,
+                ),
+                (EventType::KEY, EvdevKey::BTN_TOUCH.0, 1),
+            ] {
+                assert!(events.contains(&expected), "{:?} missing", expected);
+            }
+            emulator
+                .emulate_input(&HidEvent::Touch(contact(false)))
+                .unwrap();
+            let events = read_back(&mut touchscreen);
+            assert!(events.contains(&(
+                EventType::ABSOLUTE,
+                AbsoluteAxisCode::ABS_MT_TRACKING_ID.0,
+                -1
+            )));
+            assert!(events.contains(&(EventType::KEY, EvdevKey::BTN_TOUCH.0, 0)));
+
+            emulator
+                .emulate_input(&HidEvent::Pen(PenState {
+                    in_range: true,
+                    tip: true,
+                    x: 300,
+                    y: 400,
+                    pressure: 500,
+                    tilt_x: -20,
+                    tilt_y: 10,
+                }))
+                .unwrap();
+            let events = read_back(&mut pen);
+            for expected in [
+                (EventType::KEY, EvdevKey::BTN_TOOL_PEN.0, 1),
+                (EventType::KEY, EvdevKey::BTN_TOUCH.0, 1),
+                (EventType::ABSOLUTE, AbsoluteAxisCode::ABS_X.0, 300),
+                (EventType::ABSOLUTE, AbsoluteAxisCode::ABS_Y.0, 400),
+                (EventType::ABSOLUTE, AbsoluteAxisCode::ABS_PRESSURE.0, 500),
+                (EventType::ABSOLUTE, AbsoluteAxisCode::ABS_TILT_X.0, -20),
+                (EventType::ABSOLUTE, AbsoluteAxisCode::ABS_TILT_Y.0, 10),
+            ] {
+                assert!(events.contains(&expected), "{:?} missing", expected);
+            }
         }
     }
 }
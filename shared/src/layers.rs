@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use winit::{
+    event::{ElementState, MouseButton},
+    keyboard::KeyCode,
+};
+
+use crate::{codes::HidEvent, remap::RemapTable, scan_codes::HidCodes};
+
+/// Highest layer a switch can reach, layers being numbered after the reserved usages
+/// `Layer0` (the base layer) to `Layer6`
+pub const MAX_LAYER: u8 = HidCodes::Layer6 as u8 - HidCodes::Layer0 as u8;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LayerMode {
+    /// Active while the key is held
+    #[default]
+    Hold,
+    /// Every press turns the layer on or off. Toggling layer 0 turns off all toggles
+    Toggle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct LayerKey {
+    pub layer: u8,
+    #[serde(default)]
+    pub mode: LayerMode,
+}
+
+impl LayerKey {
+    /// The reserved usage of the layer, `None` past [`MAX_LAYER`]
+    pub fn usage(&self) -> Option<HidCodes> {
+        if self.layer > MAX_LAYER {
+            return None;
+        }
+        HidCodes::try_from(HidCodes::Layer0 as u8 + self.layer).ok()
+    }
+}
+
+/// Layers on top of the keyboard, e.g.
+///
+/// ```toml
+/// [switches]
+/// CapsLock = { layer = 1 }
+/// ScrollLock = { layer = 2, mode = "toggle" }
+///
+/// [layers.1.keys]
+/// KeyH = ["ArrowLeft"]
+/// KeyL = ["ArrowRight"]
+/// ```
+///
+/// A key goes through the highest active layer that maps it, down to the base layer
+/// where it's left alone
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct Keymap {
+    /// Keys that switch layers. They're never sent to the target
+    pub switches: HashMap<KeyCode, LayerKey>,
+    /// What keys and buttons do on each layer from 1 up
+    pub layers: HashMap<u8, RemapTable>,
+}
+
+/// Runs a [`Keymap`], keeping track of the active layers
+#[derive(Debug, Default, Clone)]
+pub struct Layers {
+    keymap: Keymap,
+    // Momentary layers with the key holding each of them, in press order
+    held: Vec<(KeyCode, u8)>,
+    // Bit n set while layer n is toggled on
+    toggled: u8,
+    // The layer every held key and button was pressed on, so it's released the same way
+    keys: HashMap<KeyCode, u8>,
+    buttons: HashMap<MouseButton, u8>,
+}
+
+impl Layers {
+    /// Switches to layers past [`MAX_LAYER`] are dropped
+    pub fn new(mut keymap: Keymap) -> Self {
+        keymap.switches.retain(|code, switch| {
+            let valid = switch.usage().is_some();
+            if !valid {
+                println!("Ignoring {:?}, layer {} doesn't exist", code, switch.layer);
+            }
+            valid
+        });
+        Self {
+            keymap,
+            ..Self::default()
+        }
+    }
+
+    /// Forgets held layer keys, for when capture starts over and their releases may
+    /// never come. Toggled layers stay on
+    pub fn release_all(&mut self) {
+        self.held.clear();
+        self.keys.clear();
+        self.buttons.clear();
+    }
+
+    fn is_active(&self, layer: u8) -> bool {
+        self.toggled & (1 << layer) != 0 || self.held.iter().any(|&(_, held)| held == layer)
+    }
+
+    /// Highest active layer for which `maps` is true, 0 if none is
+    fn resolve(&self, maps: impl Fn(&RemapTable) -> bool) -> u8 {
+        (1..=MAX_LAYER)
+            .rev()
+            .filter(|&layer| self.is_active(layer))
+            .find(|layer| self.keymap.layers.get(layer).is_some_and(&maps))
+            .unwrap_or(0)
+    }
+
+    fn switch(&mut self, code: KeyCode, switch: LayerKey, dir: ElementState) {
+        match (switch.mode, dir) {
+            (LayerMode::Hold, ElementState::Pressed) => self.held.push((code, switch.layer)),
+            (LayerMode::Hold, ElementState::Released) => self.held.retain(|&(key, _)| key != code),
+            (LayerMode::Toggle, ElementState::Pressed) if switch.layer == 0 => self.toggled = 0,
+            (LayerMode::Toggle, ElementState::Pressed) => self.toggled ^= 1 << switch.layer,
+            (LayerMode::Toggle, ElementState::Released) => (),
+        }
+    }
+
+    /// What `hid_event` turns into on the active layers. Layer keys turn into nothing
+    pub fn apply(&mut self, hid_event: HidEvent) -> Vec<HidEvent> {
+        let layer = match hid_event {
+            HidEvent::Key(scan_code) => {
+                let code = scan_code.code();
+                if let Some(&switch) = self.keymap.switches.get(&code) {
+                    self.switch(code, switch, scan_code.dir);
+                    return Vec::new();
+                }
+                match scan_code.dir {
+                    ElementState::Pressed => {
+                        let layer = self.resolve(|table| table.maps_key(code));
+                        self.keys.insert(code, layer);
+                        layer
+                    }
+                    ElementState::Released => self.keys.remove(&code).unwrap_or(0),
+                }
+            }
+            HidEvent::MouseButton(mouse_buttons) => {
+                let button = mouse_buttons.button();
+                match mouse_buttons.dir {
+                    ElementState::Pressed => {
+                        let layer = self.resolve(|table| table.maps_button(button));
+                        self.buttons.insert(button, layer);
+                        layer
+                    }
+                    ElementState::Released => self.buttons.remove(&button).unwrap_or(0),
+                }
+            }
+            _ => 0,
+        };
+        match self.keymap.layers.get(&layer) {
+            Some(table) if layer != 0 => table.apply(hid_event),
+            _ => vec![hid_event],
+        }
+    }
+}
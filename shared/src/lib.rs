@@ -1,3 +1,11 @@
 pub mod codes;
 pub mod emulator;
+pub mod layers;
+pub mod pacing;
+pub mod queue;
+pub mod remap;
+pub mod report;
 pub mod scan_codes;
+pub mod state;
+pub mod tap_hold;
+pub mod transport;
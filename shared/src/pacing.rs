@@ -0,0 +1,279 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use tokio::{
+    runtime::Handle,
+    sync::{
+        mpsc::{self, UnboundedReceiver, UnboundedSender},
+        watch,
+    },
+    task::JoinHandle,
+    time::{Instant, sleep_until},
+};
+
+use crate::{
+    codes::{EmulatorStatus, HidEvent, LockState},
+    emulator::{Emulator, EmulatorError, EventKind},
+};
+
+/// Minimum time between two events of the same kind. Kinds without an interval go out
+/// as soon as everything before them has
+#[derive(Debug, Default, Clone)]
+pub struct Pacing {
+    intervals: HashMap<EventKind, Duration>,
+}
+
+impl Pacing {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// For the mouse this is the polling interval, motion arriving in between is
+    /// merged into the next report
+    pub fn set(&mut self, kind: EventKind, interval: Duration) {
+        self.intervals.insert(kind, interval);
+    }
+
+    pub fn interval(&self, kind: EventKind) -> Option<Duration> {
+        self.intervals.get(&kind).copied()
+    }
+
+    /// Gentle enough for BIOS screens and slow KVM firmware: keys and pasted characters
+    /// 20ms apart and the mouse polled at 125Hz
+    pub fn slow() -> Self {
+        let mut pacing = Self::new();
+        pacing.set(EventKind::Keyboard, Duration::from_millis(20));
+        pacing.set(EventKind::Text, Duration::from_millis(20));
+        pacing.set(EventKind::Mouse, Duration::from_millis(8));
+        pacing
+    }
+}
+
+enum Paced {
+    Input(HidEvent),
+    Text(String),
+    /// One key press or release of text typed by a backend that only sends keys, paced
+    /// as text
+    TextKey(HidEvent),
+}
+
+impl Paced {
+    fn kind(&self) -> EventKind {
+        match self {
+            Paced::Input(hid_event) => EventKind::of(hid_event),
+            Paced::Text(_) | Paced::TextKey(_) => EventKind::Text,
+        }
+    }
+}
+
+/// Spaces out the events handed to `inner` according to a [`Pacing`].
+///
+/// Events are queued and sent in order by a background task, so `emulate_input` never
+/// waits. Failures are printed by that task, since they happen after the call returned
+pub struct PacedEmulator<E> {
+    inner: Arc<E>,
+    pacing: Pacing,
+    tx: UnboundedSender<Paced>,
+    task: JoinHandle<()>,
+}
+
+impl<E: Emulator + Send + Sync + 'static> PacedEmulator<E> {
+    pub fn new(inner: E, pacing: Pacing) -> Self {
+        let inner = Arc::new(inner);
+        let (tx, rx) = mpsc::unbounded_channel();
+        let task = Handle::current().spawn(pace_loop(inner.clone(), pacing.clone(), rx));
+        Self {
+            inner,
+            pacing,
+            tx,
+            task,
+        }
+    }
+
+    fn queue(&self, paced: Paced) -> Result<(), EmulatorError> {
+        self.tx
+            .send(paced)
+            .map_err(|_| EmulatorError::Failed("pacing task stopped".to_string()))
+    }
+}
+
+impl<E> Drop for PacedEmulator<E> {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl<E: Emulator + Send + Sync + 'static> Emulator for PacedEmulator<E> {
+    fn emulate_input(&self, hid_event: &HidEvent) -> Result<(), EmulatorError> {
+        self.queue(Paced::Input(*hid_event))
+    }
+
+    /// Paced text is handed over one character at a time, so the backend keeps typing
+    /// it its own way. Backends that can only send keys get every report paced instead
+    fn emulate_text(&self, text: &str) -> Result<(), EmulatorError> {
+        if self.pacing.interval(EventKind::Text).is_none() {
+            return self.queue(Paced::Text(text.to_string()));
+        }
+        if let Some(keys) = self.inner.text_keys(text) {
+            for hid_event in keys? {
+                self.queue(Paced::TextKey(hid_event))?;
+            }
+            return Ok(());
+        }
+        for c in text.chars() {
+            self.queue(Paced::Text(c.to_string()))?;
+        }
+        Ok(())
+    }
+
+    fn text_keys(&self, text: &str) -> Option<Result<Vec<HidEvent>, EmulatorError>> {
+        self.inner.text_keys(text)
+    }
+
+    fn status(&self) -> watch::Receiver<EmulatorStatus> {
+        self.inner.status()
+    }
+
+    fn locks(&self) -> Option<watch::Receiver<LockState>> {
+        self.inner.locks()
+    }
+}
+
+/// Folds `next` into `event` if both are motion that can go out as one report
+fn coalesce(event: &mut HidEvent, next: &Paced) -> bool {
+    let Paced::Input(next) = next else {
+        return false;
+    };
+    match (event, next) {
+        (HidEvent::MouseDelta(x, y), HidEvent::MouseDelta(dx, dy)) => {
+            *x = x.saturating_add(*dx);
+            *y = y.saturating_add(*dy);
+        }
+        (HidEvent::MouseScroll(offset), HidEvent::MouseScroll(delta)) => {
+            *offset = offset.saturating_add(*delta);
+        }
+        (event @ HidEvent::MouseAbsolute { .. }, HidEvent::MouseAbsolute { .. }) => {
+            *event = *next;
+        }
+        _ => return false,
+    }
+    true
+}
+
+async fn pace_loop<E: Emulator>(inner: Arc<E>, pacing: Pacing, mut rx: UnboundedReceiver<Paced>) {
+    let mut last: HashMap<EventKind, Instant> = HashMap::new();
+    // Taken off the queue while coalescing but not mergeable, it goes next
+    let mut next = None;
+    loop {
+        let mut paced = match next.take() {
+            Some(paced) => paced,
+            None => match rx.recv().await {
+                Some(paced) => paced,
+                None => return,
+            },
+        };
+        let kind = paced.kind();
+        // Everything behind this event waits with it, which keeps the order intact
+        if let (Some(interval), Some(&at)) = (pacing.interval(kind), last.get(&kind)) {
+            sleep_until(at + interval).await;
+        }
+        if let Paced::Input(event) = &mut paced {
+            while let Ok(queued) = rx.try_recv() {
+                if !coalesce(event, &queued) {
+                    next = Some(queued);
+                    break;
+                }
+            }
+        }
+        last.insert(kind, Instant::now());
+        let res = match paced {
+            Paced::Input(hid_event) | Paced::TextKey(hid_event) => inner.emulate_input(&hid_event),
+            Paced::Text(text) => inner.emulate_text(&text),
+        };
+        match res {
+            Err(EmulatorError::Failed(e)) => println!("{}", e),
+            Err(e @ EmulatorError::Unsupported(_)) => println!("{}", e),
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::{event::ElementState, keyboard::KeyCode};
+
+    use super::*;
+    use crate::{
+        codes::ScanCode,
+        emulator::{RecordingEmulator, us_layout_keys},
+    };
+
+    fn key(code: KeyCode, dir: ElementState) -> HidEvent {
+        HidEvent::Key(ScanCode::new(code, dir))
+    }
+
+    /// Types text through keys only, like the dongle
+    struct KeyOnlyEmulator(RecordingEmulator);
+
+    impl Emulator for KeyOnlyEmulator {
+        fn emulate_input(&self, hid_event: &HidEvent) -> Result<(), EmulatorError> {
+            self.0.emulate_input(hid_event)
+        }
+
+        fn emulate_text(&self, _text: &str) -> Result<(), EmulatorError> {
+            Err(EmulatorError::Unsupported("text".to_string()))
+        }
+
+        fn text_keys(&self, text: &str) -> Option<Result<Vec<HidEvent>, EmulatorError>> {
+            Some(us_layout_keys(text))
+        }
+    }
+
+    #[tokio::test]
+    async fn paces_every_key_of_text_for_key_only_backends() {
+        let interval = Duration::from_millis(20);
+        let mut pacing = Pacing::new();
+        pacing.set(EventKind::Text, interval);
+        let emulator = PacedEmulator::new(KeyOnlyEmulator(RecordingEmulator::new()), pacing);
+        emulator.emulate_text("A").unwrap();
+        let recording = &emulator.inner.0;
+        assert!(recording.wait_for_events(4, Duration::from_secs(1)).await);
+        recording.assert_events(&[
+            key(KeyCode::ShiftLeft, ElementState::Pressed),
+            key(KeyCode::KeyA, ElementState::Pressed),
+            key(KeyCode::KeyA, ElementState::Released),
+            key(KeyCode::ShiftLeft, ElementState::Released),
+        ]);
+        let recorded = recording.recorded();
+        for pair in recorded.windows(2) {
+            assert!(pair[1].at - pair[0].at >= interval);
+        }
+    }
+
+    #[tokio::test]
+    async fn key_only_backends_refuse_text_without_keys() {
+        let mut pacing = Pacing::new();
+        pacing.set(EventKind::Text, Duration::from_millis(1));
+        let emulator = PacedEmulator::new(KeyOnlyEmulator(RecordingEmulator::new()), pacing);
+        assert!(matches!(
+            emulator.emulate_text("aé"),
+            Err(EmulatorError::Unsupported(_))
+        ));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(emulator.inner.0.events().is_empty());
+    }
+
+    #[tokio::test]
+    async fn hands_text_over_a_character_at_a_time() {
+        let interval = Duration::from_millis(20);
+        let mut pacing = Pacing::new();
+        pacing.set(EventKind::Text, interval);
+        let emulator = PacedEmulator::new(RecordingEmulator::new(), pacing);
+        let start = Instant::now();
+        emulator.emulate_text("éA").unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(emulator.inner.text(), "é");
+        tokio::time::sleep_until(start + interval * 2).await;
+        assert_eq!(emulator.inner.text(), "éA");
+        assert!(emulator.inner.events().is_empty());
+    }
+}
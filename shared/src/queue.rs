@@ -0,0 +1,88 @@
+use std::collections::VecDeque;
+
+use crate::{codes::HidEvent, state::InputState};
+
+/// Events held back while the output device is unavailable.
+///
+/// Key and button transitions are kept so the state the client ended up in can be
+/// restored, but they're never replayed as is: stale presses would retype whatever was
+/// pressed in the meantime. Motion and scroll are collapsed into one delta each, and
+/// absolute positions, controllers, touch contacts and the pen only keep their latest
+/// snapshot.
+#[derive(Debug)]
+pub struct PendingQueue {
+    events: VecDeque<HidEvent>,
+    // Held state when the first event was queued, along with transitions pushed out of
+    // a full queue
+    base: Option<InputState>,
+    capacity: usize,
+}
+
+impl PendingQueue {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            events: VecDeque::new(),
+            base: None,
+            capacity,
+        }
+    }
+
+    /// Queues `event`. `held` is what the target was last told is held down
+    pub fn push(&mut self, event: HidEvent, held: &InputState) {
+        let base = self.base.get_or_insert_with(|| held.clone());
+        let existing = self
+            .events
+            .iter_mut()
+            .find(|queued| match (&**queued, &event) {
+                (HidEvent::MouseDelta(..), HidEvent::MouseDelta(..))
+                | (HidEvent::MouseScroll(_), HidEvent::MouseScroll(_))
+                | (HidEvent::MouseAbsolute { .. }, HidEvent::MouseAbsolute { .. })
+                | (HidEvent::Pen(_), HidEvent::Pen(_)) => true,
+                (HidEvent::Touch(a), HidEvent::Touch(b)) => a.id == b.id,
+                (HidEvent::Gamepad(a), HidEvent::Gamepad(b)) => a.id == b.id,
+                _ => false,
+            });
+        match (existing, event) {
+            (Some(HidEvent::MouseDelta(x, y)), HidEvent::MouseDelta(dx, dy)) => {
+                *x = x.saturating_add(dx);
+                *y = y.saturating_add(dy);
+            }
+            (Some(HidEvent::MouseScroll(scroll)), HidEvent::MouseScroll(offset)) => {
+                *scroll = scroll.saturating_add(offset);
+            }
+            (Some(queued), event) => *queued = event,
+            (None, event) => self.events.push_back(event),
+        }
+
+        if self.events.len() > self.capacity
+            && let Some(oldest) = self.events.pop_front()
+        {
+            base.apply(&oldest);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.base.is_none()
+    }
+
+    /// Keys and buttons that should be held once everything queued is applied
+    pub fn desired(&self) -> InputState {
+        let mut state = self.base.clone().unwrap_or_default();
+        for event in &self.events {
+            state.apply(event);
+        }
+        state
+    }
+
+    /// Queued events worth sending after the state is restored, in order
+    pub fn replay(&self) -> impl Iterator<Item = &HidEvent> {
+        self.events
+            .iter()
+            .filter(|event| !matches!(event, HidEvent::Key(_) | HidEvent::MouseButton(_)))
+    }
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+        self.base = None;
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use winit::{
+    event::{ElementState, MouseButton},
+    keyboard::KeyCode,
+};
+
+use crate::codes::{HidEvent, MouseButtons, ScanCode};
+
+/// A key or mouse button a remapped input turns into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum Output {
+    Key(KeyCode),
+    Button(MouseButton),
+}
+
+impl Output {
+    fn event(self, dir: ElementState) -> HidEvent {
+        match self {
+            Output::Key(code) => HidEvent::Key(ScanCode::new(code, dir)),
+            Output::Button(button) => HidEvent::MouseButton(MouseButtons::new(button, dir)),
+        }
+    }
+}
+
+/// Rewrites keys and mouse buttons before they're emulated, e.g.
+///
+/// ```toml
+/// [keys]
+/// CapsLock = ["ControlLeft"]
+/// ControlLeft = ["CapsLock"]
+/// Insert = []
+///
+/// [buttons]
+/// Back = ["AltLeft", "ArrowLeft"]
+/// ```
+///
+/// Every input maps to a chord that's pressed in order and released in reverse. An
+/// empty chord disables the input. Anything not in the table is left alone
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct RemapTable {
+    keys: HashMap<KeyCode, Vec<Output>>,
+    buttons: HashMap<MouseButton, Vec<Output>>,
+}
+
+impl RemapTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn map_key(&mut self, from: KeyCode, to: Vec<Output>) {
+        self.keys.insert(from, to);
+    }
+
+    pub fn map_button(&mut self, from: MouseButton, to: Vec<Output>) {
+        self.buttons.insert(from, to);
+    }
+
+    pub fn maps_key(&self, code: KeyCode) -> bool {
+        self.keys.contains_key(&code)
+    }
+
+    pub fn maps_button(&self, button: MouseButton) -> bool {
+        self.buttons.contains_key(&button)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty() && self.buttons.is_empty()
+    }
+
+    /// What `hid_event` turns into, itself if it isn't remapped
+    pub fn apply(&self, hid_event: HidEvent) -> Vec<HidEvent> {
+        let (chord, dir) = match hid_event {
+            HidEvent::Key(scan_code) => (self.keys.get(&scan_code.code()), scan_code.dir),
+            HidEvent::MouseButton(mouse_buttons) => {
+                (self.buttons.get(&mouse_buttons.button()), mouse_buttons.dir)
+            }
+            _ => (None, ElementState::Pressed),
+        };
+        let Some(chord) = chord else {
+            return vec![hid_event];
+        };
+        let events = chord.iter().map(|output| output.event(dir));
+        match dir {
+            ElementState::Pressed => events.collect(),
+            ElementState::Released => events.rev().collect(),
+        }
+    }
+}
@@ -0,0 +1,398 @@
+use crate::scan_codes::HidCodes;
+
+/// Written in place of a report id to devices that don't number their reports, the way
+/// hidapi expects it
+pub const NO_REPORT_ID: u8 = 0;
+/// Report id prepended to NKRO keyboard reports
+pub const NKRO_REPORT_ID: u8 = 3;
+/// Report id prepended to consumer control reports
+pub const CONSUMER_REPORT_ID: u8 = 4;
+
+/// One bit for every keyboard usage from 0x00 up to RightGUI (0xE7)
+const NKRO_BITMAP_LEN: usize = (HidCodes::KeyboardRightGUI as usize + 1) / 8;
+
+/// Usage reported in every key slot when more than six keys are held
+const ERROR_ROLL_OVER: u8 = 0x01;
+
+/// Largest motion [`split_delta`] passes on along either axis. Anything past this is a
+/// bogus delta rather than a flick, and would take thousands of reports to send
+pub const MAX_DELTA: i32 = 4096;
+
+/// Splits a relative motion into steps that each fit an i8 report field, so a fast flick
+/// doesn't wrap around. Motion is capped at [`MAX_DELTA`] along each axis first and
+/// anything past it is dropped, so the steps add up to `(x, y)` only within that cap
+pub fn split_delta(x: i32, y: i32) -> impl Iterator<Item = (i8, i8)> {
+    let mut x = x.clamp(-MAX_DELTA, MAX_DELTA);
+    let mut y = y.clamp(-MAX_DELTA, MAX_DELTA);
+    std::iter::from_fn(move || {
+        if x == 0 && y == 0 {
+            return None;
+        }
+        let step_x = x.clamp(i8::MIN as i32, i8::MAX as i32);
+        let step_y = y.clamp(i8::MIN as i32, i8::MAX as i32);
+        x -= step_x;
+        y -= step_y;
+        Some((step_x as i8, step_y as i8))
+    })
+}
+
+/// What kind of reports a [`HidEmulator`](crate::emulator::HidEmulator) writes
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ReportMode {
+    /// `[0, HidType, ..]` packets understood by our own dongle firmware
+    #[default]
+    Custom,
+    /// Standard boot protocol keyboard and mouse reports, for stock HID proxy boards
+    Boot,
+    /// Like [`ReportMode::Boot`] but the keyboard is sent as a bitmap of every key, so any
+    /// number of keys can be held at once. Falls back to boot reports if the device's
+    /// descriptor has no NKRO report
+    Nkro,
+}
+
+/// Keyboard state needed to build boot reports, since every report carries all held keys
+#[derive(Debug, Default, Clone)]
+pub struct KeyboardReport {
+    modifiers: u8,
+    // In press order so the oldest six are reported on rollover
+    keys: Vec<u8>,
+}
+
+impl KeyboardReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Updates the held keys. Returns false if nothing changed, meaning no report is needed
+    pub fn set(&mut self, code: HidCodes, pressed: bool) -> bool {
+        let code = code as u8;
+        if (HidCodes::KeyboardLeftControl as u8..=HidCodes::KeyboardRightGUI as u8).contains(&code)
+        {
+            let bit = 1 << (code - HidCodes::KeyboardLeftControl as u8);
+            let before = self.modifiers;
+            if pressed {
+                self.modifiers |= bit;
+            } else {
+                self.modifiers &= !bit;
+            }
+            return before != self.modifiers;
+        }
+
+        let pos = self.keys.iter().position(|&key| key == code);
+        match (pressed, pos) {
+            (true, None) => self.keys.push(code),
+            (false, Some(pos)) => {
+                self.keys.remove(pos);
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.modifiers = 0;
+        self.keys.clear();
+    }
+
+    /// `[id, modifiers, reserved, key * 6]`, the id being [`NO_REPORT_ID`] for a boot
+    /// keyboard
+    pub fn report(&self, id: u8) -> [u8; 9] {
+        let mut buf = [0u8; 9];
+        buf[0] = id;
+        buf[1] = self.modifiers;
+        if self.keys.len() > 6 {
+            buf[3..].fill(ERROR_ROLL_OVER);
+        } else {
+            buf[3..3 + self.keys.len()].copy_from_slice(&self.keys);
+        }
+        buf
+    }
+
+    /// `[id, bitmap]` where bit `n` of the bitmap is set while usage `n` is held.
+    /// Modifiers are part of the bitmap, making up its last byte
+    pub fn nkro_report(&self) -> [u8; NKRO_BITMAP_LEN + 1] {
+        let mut buf = [0u8; NKRO_BITMAP_LEN + 1];
+        buf[0] = NKRO_REPORT_ID;
+        for &key in &self.keys {
+            buf[1 + key as usize / 8] |= 1 << (key % 8);
+        }
+        buf[NKRO_BITMAP_LEN] = self.modifiers;
+        buf
+    }
+}
+
+/// Reports a device declares in its report descriptor
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Report id of the keyboard input, [`NO_REPORT_ID`] if the device doesn't number its
+    /// reports. `None` without a keyboard, as on the mouse interface of a boot device
+    pub keyboard: Option<u8>,
+    /// Report id of the relative mouse input, like `keyboard`
+    pub mouse: Option<u8>,
+    /// The bitmap that [`KeyboardReport::nkro_report`] produces: an input of 1 bit
+    /// variable fields on the keyboard page, covering usages 0x00 to 0xE7 under
+    /// [`NKRO_REPORT_ID`]
+    pub nkro: bool,
+    /// Any input on the consumer page under [`CONSUMER_REPORT_ID`]
+    pub consumer: bool,
+}
+
+impl Capabilities {
+    pub fn parse(descriptor: &[u8]) -> Self {
+        let mut caps = Self::default();
+        for_each_input(descriptor, |input| {
+            let nkro = input.usage_page == 0x07
+                && input.report_id == NKRO_REPORT_ID as u32
+                && input.report_size == 1
+                && input.variable
+                && input.usage_min == Some(0)
+                && input.usage_max == Some(HidCodes::KeyboardRightGUI as u32);
+            caps.nkro |= nkro;
+            if input.usage_page == 0x07 && !nkro && caps.keyboard.is_none() {
+                caps.keyboard = Some(input.report_id as u8);
+            }
+            // Relative X on the generic desktop page
+            if input.usage_page == 0x01 && input.relative && input.has_usage(0x30) {
+                caps.mouse.get_or_insert(input.report_id as u8);
+            }
+            caps.consumer |=
+                input.usage_page == 0x0C && input.report_id == CONSUMER_REPORT_ID as u32;
+        });
+        caps
+    }
+}
+
+/// Global and local state in effect for an Input main item
+struct InputItem {
+    usage_page: u32,
+    report_size: u32,
+    report_id: u32,
+    usages: Vec<u32>,
+    usage_min: Option<u32>,
+    usage_max: Option<u32>,
+    variable: bool,
+    relative: bool,
+}
+
+impl InputItem {
+    fn has_usage(&self, usage: u32) -> bool {
+        self.usages.contains(&usage)
+            || self
+                .usage_min
+                .zip(self.usage_max)
+                .is_some_and(|(min, max)| (min..=max).contains(&usage))
+    }
+}
+
+fn for_each_input(descriptor: &[u8], mut f: impl FnMut(&InputItem)) {
+    let (mut usage_page, mut report_size, mut report_id) = (0, 0, 0);
+    let (mut usage_min, mut usage_max) = (None, None);
+    let mut usages = Vec::new();
+    let mut i = 0;
+    while i < descriptor.len() {
+        let prefix = descriptor[i];
+        // Long items aren't used by anything we care about
+        if prefix == 0xFE {
+            let len = *descriptor.get(i + 1).unwrap_or(&0) as usize;
+            i += 3 + len;
+            continue;
+        }
+        let len = match prefix & 0x03 {
+            3 => 4,
+            len => len as usize,
+        };
+        let Some(data) = descriptor.get(i + 1..i + 1 + len) else {
+            break;
+        };
+        let value = data
+            .iter()
+            .rev()
+            .fold(0u32, |acc, &byte| acc << 8 | byte as u32);
+        i += 1 + len;
+
+        match prefix & 0xFC {
+            // Usage Page
+            0x04 => usage_page = value,
+            // Report Size
+            0x74 => report_size = value,
+            // Report ID
+            0x84 => report_id = value,
+            // Usage
+            0x08 => usages.push(value),
+            // Usage Minimum
+            0x18 => usage_min = Some(value),
+            // Usage Maximum
+            0x28 => usage_max = Some(value),
+            // Input
+            0x80 => {
+                f(&InputItem {
+                    usage_page,
+                    report_size,
+                    report_id,
+                    usages: std::mem::take(&mut usages),
+                    usage_min,
+                    usage_max,
+                    variable: value & 0x02 != 0,
+                    relative: value & 0x04 != 0,
+                });
+                (usage_min, usage_max) = (None, None);
+            }
+            // Output, Feature and Collection also end the local items
+            0x90 | 0xB0 | 0xA0 | 0xC0 => {
+                usages.clear();
+                (usage_min, usage_max) = (None, None);
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Held consumer controls. Only one usage is reported at a time, the latest one pressed
+#[derive(Debug, Default, Clone)]
+pub struct ConsumerReport {
+    held: Vec<u16>,
+}
+
+impl ConsumerReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns false if nothing changed
+    pub fn set(&mut self, usage: u16, pressed: bool) -> bool {
+        let pos = self.held.iter().position(|&held| held == usage);
+        match (pressed, pos) {
+            (true, None) => self.held.push(usage),
+            (false, Some(pos)) => {
+                self.held.remove(pos);
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.held.clear();
+    }
+
+    /// `[id, usage le]`, a usage of 0 meaning nothing is pressed
+    pub fn report(&self) -> [u8; 3] {
+        let [lo, hi] = self.held.last().copied().unwrap_or(0).to_le_bytes();
+        [CONSUMER_REPORT_ID, lo, hi]
+    }
+}
+
+/// Held mouse buttons. Motion and scroll are relative, so they're passed per report
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MouseReport {
+    buttons: u8,
+}
+
+impl MouseReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Updates the button mask. Returns false if nothing changed or `code` isn't a button
+    pub fn set(&mut self, code: HidCodes, pressed: bool) -> bool {
+        let code = code as u8;
+        if !(HidCodes::MouseLeftClick as u8..=HidCodes::Mouse5 as u8).contains(&code) {
+            return false;
+        }
+        let bit = 1 << (code - HidCodes::MouseLeftClick as u8);
+        let before = self.buttons;
+        if pressed {
+            self.buttons |= bit;
+        } else {
+            self.buttons &= !bit;
+        }
+        before != self.buttons
+    }
+
+    pub fn clear(&mut self) {
+        self.buttons = 0;
+    }
+
+    /// `[id, buttons, dx, dy, wheel]`, the id being [`NO_REPORT_ID`] for a boot mouse
+    pub fn report(&self, id: u8, dx: i8, dy: i8, wheel: i8) -> [u8; 5] {
+        [id, self.buttons, dx as u8, dy as u8, wheel as u8]
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// The boot keyboard from appendix B.1 of the HID spec
+    pub(crate) const BOOT_KEYBOARD: &[u8] = &[
+        0x05, 0x01, 0x09, 0x06, 0xA1, 0x01, 0x05, 0x07, 0x19, 0xE0, 0x29, 0xE7, 0x15, 0x00, 0x25,
+        0x01, 0x75, 0x01, 0x95, 0x08, 0x81, 0x02, 0x95, 0x01, 0x75, 0x08, 0x81, 0x01, 0x95, 0x05,
+        0x75, 0x01, 0x05, 0x08, 0x19, 0x01, 0x29, 0x05, 0x91, 0x02, 0x95, 0x01, 0x75, 0x03, 0x91,
+        0x01, 0x95, 0x06, 0x75, 0x08, 0x15, 0x00, 0x25, 0x65, 0x05, 0x07, 0x19, 0x00, 0x29, 0x65,
+        0x81, 0x00, 0xC0,
+    ];
+
+    /// The boot mouse from appendix B.2 of the HID spec
+    pub(crate) const BOOT_MOUSE: &[u8] = &[
+        0x05, 0x01, 0x09, 0x02, 0xA1, 0x01, 0x09, 0x01, 0xA1, 0x00, 0x05, 0x09, 0x19, 0x01, 0x29,
+        0x03, 0x15, 0x00, 0x25, 0x01, 0x95, 0x03, 0x75, 0x01, 0x81, 0x02, 0x95, 0x01, 0x75, 0x05,
+        0x81, 0x01, 0x05, 0x01, 0x09, 0x30, 0x09, 0x31, 0x15, 0x81, 0x25, 0x7F, 0x75, 0x08, 0x95,
+        0x02, 0x81, 0x06, 0xC0, 0xC0,
+    ];
+
+    fn steps(x: i32, y: i32) -> Vec<(i8, i8)> {
+        split_delta(x, y).collect()
+    }
+
+    #[test]
+    fn splits_deltas_into_i8_steps() {
+        assert_eq!(steps(0, 0), []);
+        assert_eq!(steps(127, -127), [(127, -127)]);
+        assert_eq!(steps(-128, 0), [(-128, 0)]);
+        assert_eq!(steps(128, -128), [(127, -128), (1, 0)]);
+        assert_eq!(steps(-129, 128), [(-128, 127), (-1, 1)]);
+    }
+
+    #[test]
+    fn clamps_huge_deltas() {
+        for (x, y) in [(i32::MAX, 0), (i32::MIN, i32::MAX), (0, i32::MIN)] {
+            let steps = steps(x, y);
+            let sum = |axis: fn(&(i8, i8)) -> i8| steps.iter().map(|s| axis(s) as i32).sum();
+            assert_eq!(
+                (sum(|s| s.0), sum(|s| s.1)),
+                (
+                    x.clamp(-MAX_DELTA, MAX_DELTA),
+                    y.clamp(-MAX_DELTA, MAX_DELTA)
+                )
+            );
+            assert!(steps.len() <= MAX_DELTA as usize / 127 + 1);
+        }
+    }
+
+    #[test]
+    fn parses_boot_keyboard() {
+        let caps = Capabilities::parse(BOOT_KEYBOARD);
+        assert_eq!(caps.keyboard, Some(NO_REPORT_ID));
+        assert_eq!(caps.mouse, None);
+        assert!(!caps.nkro && !caps.consumer);
+    }
+
+    #[test]
+    fn parses_boot_mouse() {
+        let caps = Capabilities::parse(BOOT_MOUSE);
+        assert_eq!(caps.keyboard, None);
+        assert_eq!(caps.mouse, Some(NO_REPORT_ID));
+    }
+
+    #[test]
+    fn parses_numbered_reports() {
+        // Both collections of the two descriptors on one interface, under ids 1 and 2
+        let mut descriptor = vec![0x85, 0x01];
+        descriptor.extend_from_slice(BOOT_KEYBOARD);
+        descriptor.extend_from_slice(&[0x85, 0x02]);
+        descriptor.extend_from_slice(BOOT_MOUSE);
+        let caps = Capabilities::parse(&descriptor);
+        assert_eq!(caps.keyboard, Some(1));
+        assert_eq!(caps.mouse, Some(2));
+    }
+}
@@ -1,3 +1,5 @@
+use winit::keyboard::KeyCode;
+
 /// Keyboard Keycodes
 #[repr(u8)]
 #[allow(unused)]
@@ -466,3 +468,712 @@ pub enum HidCodes {
     Mouse4 = 0xF7,
     Mouse5 = 0xF8,
 }
+
+impl TryFrom<u8> for HidCodes {
+    /// The value, which isn't a usage we know
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, u8> {
+        match value {
+            0x01 => Ok(HidCodes::KeyboardErrorRollOver),
+            0x02 => Ok(HidCodes::KeyboardPOSTFail),
+            0x03 => Ok(HidCodes::KeyboardErrorUndefined),
+            0x04 => Ok(HidCodes::KeyboardAa),
+            0x05 => Ok(HidCodes::KeyboardBb),
+            0x06 => Ok(HidCodes::KeyboardCc),
+            0x07 => Ok(HidCodes::KeyboardDd),
+            0x08 => Ok(HidCodes::KeyboardEe),
+            0x09 => Ok(HidCodes::KeyboardFf),
+            0x0A => Ok(HidCodes::KeyboardGg),
+            0x0B => Ok(HidCodes::KeyboardHh),
+            0x0C => Ok(HidCodes::KeyboardIi),
+            0x0D => Ok(HidCodes::KeyboardJj),
+            0x0E => Ok(HidCodes::KeyboardKk),
+            0x0F => Ok(HidCodes::KeyboardLl),
+            0x10 => Ok(HidCodes::KeyboardMm),
+            0x11 => Ok(HidCodes::KeyboardNn),
+            0x12 => Ok(HidCodes::KeyboardOo),
+            0x13 => Ok(HidCodes::KeyboardPp),
+            0x14 => Ok(HidCodes::KeyboardQq),
+            0x15 => Ok(HidCodes::KeyboardRr),
+            0x16 => Ok(HidCodes::KeyboardSs),
+            0x17 => Ok(HidCodes::KeyboardTt),
+            0x18 => Ok(HidCodes::KeyboardUu),
+            0x19 => Ok(HidCodes::KeyboardVv),
+            0x1A => Ok(HidCodes::KeyboardWw),
+            0x1B => Ok(HidCodes::KeyboardXx),
+            0x1C => Ok(HidCodes::KeyboardYy),
+            0x1D => Ok(HidCodes::KeyboardZz),
+            0x1E => Ok(HidCodes::Keyboard1Exclamation),
+            0x1F => Ok(HidCodes::Keyboard2At),
+            0x20 => Ok(HidCodes::Keyboard3Hash),
+            0x21 => Ok(HidCodes::Keyboard4Dollar),
+            0x22 => Ok(HidCodes::Keyboard5Percent),
+            0x23 => Ok(HidCodes::Keyboard6Caret),
+            0x24 => Ok(HidCodes::Keyboard7Ampersand),
+            0x25 => Ok(HidCodes::Keyboard8Asterisk),
+            0x26 => Ok(HidCodes::Keyboard9OpenParens),
+            0x27 => Ok(HidCodes::Keyboard0CloseParens),
+            0x28 => Ok(HidCodes::KeyboardEnter),
+            0x29 => Ok(HidCodes::KeyboardEscape),
+            0x2A => Ok(HidCodes::KeyboardBackspace),
+            0x2B => Ok(HidCodes::KeyboardTab),
+            0x2C => Ok(HidCodes::KeyboardSpacebar),
+            0x2D => Ok(HidCodes::KeyboardDashUnderscore),
+            0x2E => Ok(HidCodes::KeyboardEqualPlus),
+            0x2F => Ok(HidCodes::KeyboardOpenBracketBrace),
+            0x30 => Ok(HidCodes::KeyboardCloseBracketBrace),
+            0x31 => Ok(HidCodes::KeyboardBackslashBar),
+            0x32 => Ok(HidCodes::KeyboardNonUSHash),
+            0x33 => Ok(HidCodes::KeyboardSemiColon),
+            0x34 => Ok(HidCodes::KeyboardSingleDoubleQuote),
+            0x35 => Ok(HidCodes::KeyboardBacktickTilde),
+            0x36 => Ok(HidCodes::KeyboardCommaLess),
+            0x37 => Ok(HidCodes::KeyboardPeriodGreater),
+            0x38 => Ok(HidCodes::KeyboardSlashQuestion),
+            0x39 => Ok(HidCodes::KeyboardCapsLock),
+            0x3A => Ok(HidCodes::KeyboardF1),
+            0x3B => Ok(HidCodes::KeyboardF2),
+            0x3C => Ok(HidCodes::KeyboardF3),
+            0x3D => Ok(HidCodes::KeyboardF4),
+            0x3E => Ok(HidCodes::KeyboardF5),
+            0x3F => Ok(HidCodes::KeyboardF6),
+            0x40 => Ok(HidCodes::KeyboardF7),
+            0x41 => Ok(HidCodes::KeyboardF8),
+            0x42 => Ok(HidCodes::KeyboardF9),
+            0x43 => Ok(HidCodes::KeyboardF10),
+            0x44 => Ok(HidCodes::KeyboardF11),
+            0x45 => Ok(HidCodes::KeyboardF12),
+            0x46 => Ok(HidCodes::KeyboardPrintScreen),
+            0x47 => Ok(HidCodes::KeyboardScrollLock),
+            0x48 => Ok(HidCodes::KeyboardPause),
+            0x49 => Ok(HidCodes::KeyboardInsert),
+            0x4A => Ok(HidCodes::KeyboardHome),
+            0x4B => Ok(HidCodes::KeyboardPageUp),
+            0x4C => Ok(HidCodes::KeyboardDelete),
+            0x4D => Ok(HidCodes::KeyboardEnd),
+            0x4E => Ok(HidCodes::KeyboardPageDown),
+            0x4F => Ok(HidCodes::KeyboardRightArrow),
+            0x50 => Ok(HidCodes::KeyboardLeftArrow),
+            0x51 => Ok(HidCodes::KeyboardDownArrow),
+            0x52 => Ok(HidCodes::KeyboardUpArrow),
+            0x53 => Ok(HidCodes::KeypadNumLock),
+            0x54 => Ok(HidCodes::KeypadDivide),
+            0x55 => Ok(HidCodes::KeypadMultiply),
+            0x56 => Ok(HidCodes::KeypadMinus),
+            0x57 => Ok(HidCodes::KeypadPlus),
+            0x58 => Ok(HidCodes::KeypadEnter),
+            0x59 => Ok(HidCodes::Keypad1End),
+            0x5A => Ok(HidCodes::Keypad2DownArrow),
+            0x5B => Ok(HidCodes::Keypad3PageDown),
+            0x5C => Ok(HidCodes::Keypad4LeftArrow),
+            0x5D => Ok(HidCodes::Keypad5),
+            0x5E => Ok(HidCodes::Keypad6RightArrow),
+            0x5F => Ok(HidCodes::Keypad7Home),
+            0x60 => Ok(HidCodes::Keypad8UpArrow),
+            0x61 => Ok(HidCodes::Keypad9PageUp),
+            0x62 => Ok(HidCodes::Keypad0Insert),
+            0x63 => Ok(HidCodes::KeypadPeriodDelete),
+            0x64 => Ok(HidCodes::KeyboardNonUSSlash),
+            0x65 => Ok(HidCodes::KeyboardApplication),
+            0x66 => Ok(HidCodes::KeyboardPower),
+            0x67 => Ok(HidCodes::KeypadEqual),
+            0x68 => Ok(HidCodes::KeyboardF13),
+            0x69 => Ok(HidCodes::KeyboardF14),
+            0x6A => Ok(HidCodes::KeyboardF15),
+            0x6B => Ok(HidCodes::KeyboardF16),
+            0x6C => Ok(HidCodes::KeyboardF17),
+            0x6D => Ok(HidCodes::KeyboardF18),
+            0x6E => Ok(HidCodes::KeyboardF19),
+            0x6F => Ok(HidCodes::KeyboardF20),
+            0x70 => Ok(HidCodes::KeyboardF21),
+            0x71 => Ok(HidCodes::KeyboardF22),
+            0x72 => Ok(HidCodes::KeyboardF23),
+            0x73 => Ok(HidCodes::KeyboardF24),
+            0x74 => Ok(HidCodes::KeyboardExecute),
+            0x75 => Ok(HidCodes::KeyboardHelp),
+            0x76 => Ok(HidCodes::KeyboardMenu),
+            0x77 => Ok(HidCodes::KeyboardSelect),
+            0x78 => Ok(HidCodes::KeyboardStop),
+            0x79 => Ok(HidCodes::KeyboardAgain),
+            0x7A => Ok(HidCodes::KeyboardUndo),
+            0x7B => Ok(HidCodes::KeyboardCut),
+            0x7C => Ok(HidCodes::KeyboardCopy),
+            0x7D => Ok(HidCodes::KeyboardPaste),
+            0x7E => Ok(HidCodes::KeyboardFind),
+            0x7F => Ok(HidCodes::KeyboardMute),
+            0x80 => Ok(HidCodes::KeyboardVolumeUp),
+            0x81 => Ok(HidCodes::KeyboardVolumeDown),
+            0x82 => Ok(HidCodes::KeyboardLockingCapsLock),
+            0x83 => Ok(HidCodes::KeyboardLockingNumLock),
+            0x84 => Ok(HidCodes::KeyboardLockingScrollLock),
+            0x85 => Ok(HidCodes::KeypadComma),
+            0x86 => Ok(HidCodes::KeypadEqualSign),
+            0x87 => Ok(HidCodes::KeyboardInternational1),
+            0x88 => Ok(HidCodes::KeyboardInternational2),
+            0x89 => Ok(HidCodes::KeyboardInternational3),
+            0x8A => Ok(HidCodes::KeyboardInternational4),
+            0x8B => Ok(HidCodes::KeyboardInternational5),
+            0x8C => Ok(HidCodes::KeyboardInternational6),
+            0x8D => Ok(HidCodes::KeyboardInternational7),
+            0x8E => Ok(HidCodes::KeyboardInternational8),
+            0x8F => Ok(HidCodes::KeyboardInternational9),
+            0x90 => Ok(HidCodes::KeyboardLANG1),
+            0x91 => Ok(HidCodes::KeyboardLANG2),
+            0x92 => Ok(HidCodes::KeyboardLANG3),
+            0x93 => Ok(HidCodes::KeyboardLANG4),
+            0x94 => Ok(HidCodes::KeyboardLANG5),
+            0x95 => Ok(HidCodes::KeyboardLANG6),
+            0x96 => Ok(HidCodes::KeyboardLANG7),
+            0x97 => Ok(HidCodes::KeyboardLANG8),
+            0x98 => Ok(HidCodes::KeyboardLANG9),
+            0x99 => Ok(HidCodes::KeyboardAlternateErase),
+            0x9A => Ok(HidCodes::KeyboardSysReqAttention),
+            0x9B => Ok(HidCodes::KeyboardCancel),
+            0x9C => Ok(HidCodes::KeyboardClear),
+            0x9D => Ok(HidCodes::KeyboardPrior),
+            0x9E => Ok(HidCodes::KeyboardReturn),
+            0x9F => Ok(HidCodes::KeyboardSeparator),
+            0xA0 => Ok(HidCodes::KeyboardOut),
+            0xA1 => Ok(HidCodes::KeyboardOper),
+            0xA2 => Ok(HidCodes::KeyboardClearAgain),
+            0xA3 => Ok(HidCodes::KeyboardCrSelProps),
+            0xA4 => Ok(HidCodes::KeyboardExSel),
+            0xB0 => Ok(HidCodes::Keypad00),
+            0xB1 => Ok(HidCodes::Keypad000),
+            0xB2 => Ok(HidCodes::ThousandsSeparator),
+            0xB3 => Ok(HidCodes::DecimalSeparator),
+            0xB4 => Ok(HidCodes::CurrencyUnit),
+            0xB5 => Ok(HidCodes::CurrencySubunit),
+            0xB6 => Ok(HidCodes::KeypadOpenParens),
+            0xB7 => Ok(HidCodes::KeypadCloseParens),
+            0xB8 => Ok(HidCodes::KeypadOpenBrace),
+            0xB9 => Ok(HidCodes::KeypadCloseBrace),
+            0xBA => Ok(HidCodes::KeypadTab),
+            0xBB => Ok(HidCodes::KeypadBackspace),
+            0xBC => Ok(HidCodes::KeypadA),
+            0xBD => Ok(HidCodes::KeypadB),
+            0xBE => Ok(HidCodes::KeypadC),
+            0xBF => Ok(HidCodes::KeypadD),
+            0xC0 => Ok(HidCodes::KeypadE),
+            0xC1 => Ok(HidCodes::KeypadF),
+            0xC2 => Ok(HidCodes::KeypadBitwiseXor),
+            0xC3 => Ok(HidCodes::KeypadLogicalXor),
+            0xC4 => Ok(HidCodes::KeypadModulo),
+            0xC5 => Ok(HidCodes::KeypadLeftShift),
+            0xC6 => Ok(HidCodes::KeypadRightShift),
+            0xC7 => Ok(HidCodes::KeypadBitwiseAnd),
+            0xC8 => Ok(HidCodes::KeypadLogicalAnd),
+            0xC9 => Ok(HidCodes::KeypadBitwiseOr),
+            0xCA => Ok(HidCodes::KeypadLogicalOr),
+            0xCB => Ok(HidCodes::KeypadColon),
+            0xCC => Ok(HidCodes::KeypadHash),
+            0xCD => Ok(HidCodes::KeypadSpace),
+            0xCE => Ok(HidCodes::KeypadAt),
+            0xCF => Ok(HidCodes::KeypadExclamation),
+            0xD0 => Ok(HidCodes::KeypadMemoryStore),
+            0xD1 => Ok(HidCodes::KeypadMemoryRecall),
+            0xD2 => Ok(HidCodes::KeypadMemoryClear),
+            0xD3 => Ok(HidCodes::KeypadMemoryAdd),
+            0xD4 => Ok(HidCodes::KeypadMemorySubtract),
+            0xD5 => Ok(HidCodes::KeypadMemoryMultiply),
+            0xD6 => Ok(HidCodes::KeypadMemoryDivide),
+            0xD7 => Ok(HidCodes::KeypadPositiveNegative),
+            0xD8 => Ok(HidCodes::KeypadClear),
+            0xD9 => Ok(HidCodes::KeypadClearEntry),
+            0xDA => Ok(HidCodes::KeypadBinary),
+            0xDB => Ok(HidCodes::KeypadOctal),
+            0xDC => Ok(HidCodes::KeypadDecimal),
+            0xDD => Ok(HidCodes::KeypadHexadecimal),
+            0xE0 => Ok(HidCodes::KeyboardLeftControl),
+            0xE1 => Ok(HidCodes::KeyboardLeftShift),
+            0xE2 => Ok(HidCodes::KeyboardLeftAlt),
+            0xE3 => Ok(HidCodes::KeyboardLeftGUI),
+            0xE4 => Ok(HidCodes::KeyboardRightControl),
+            0xE5 => Ok(HidCodes::KeyboardRightShift),
+            0xE6 => Ok(HidCodes::KeyboardRightAlt),
+            0xE7 => Ok(HidCodes::KeyboardRightGUI),
+            0xE8 => Ok(HidCodes::Reserved),
+            0xE9 => Ok(HidCodes::Layer0),
+            0xEA => Ok(HidCodes::Layer1),
+            0xEB => Ok(HidCodes::Layer2),
+            0xEC => Ok(HidCodes::Layer3),
+            0xED => Ok(HidCodes::Layer4),
+            0xEE => Ok(HidCodes::Layer5),
+            0xEF => Ok(HidCodes::Layer6),
+            0xF0 => Ok(HidCodes::Layer7),
+            0xF1 => Ok(HidCodes::Layer8),
+            0xF2 => Ok(HidCodes::Layer9),
+            0xF3 => Ok(HidCodes::Layer10),
+            0xF4 => Ok(HidCodes::MouseLeftClick),
+            0xF5 => Ok(HidCodes::MouseRightClick),
+            0xF6 => Ok(HidCodes::MouseMiddleClick),
+            0xF7 => Ok(HidCodes::Mouse4),
+            0xF8 => Ok(HidCodes::Mouse5),
+            _ => Err(value),
+        }
+    }
+}
+
+impl From<HidCodes> for u8 {
+    fn from(code: HidCodes) -> u8 {
+        code as u8
+    }
+}
+
+impl HidCodes {
+    /// The key that [`ScanCode::to_hid`](crate::codes::ScanCode::to_hid) turns into this
+    /// usage. Usages no key is sent as give back nothing
+    pub fn to_key_code(self) -> Option<KeyCode> {
+        match self {
+            HidCodes::KeyboardAa => Some(KeyCode::KeyA),
+            HidCodes::KeyboardBb => Some(KeyCode::KeyB),
+            HidCodes::KeyboardCc => Some(KeyCode::KeyC),
+            HidCodes::KeyboardDd => Some(KeyCode::KeyD),
+            HidCodes::KeyboardEe => Some(KeyCode::KeyE),
+            HidCodes::KeyboardFf => Some(KeyCode::KeyF),
+            HidCodes::KeyboardGg => Some(KeyCode::KeyG),
+            HidCodes::KeyboardHh => Some(KeyCode::KeyH),
+            HidCodes::KeyboardIi => Some(KeyCode::KeyI),
+            HidCodes::KeyboardJj => Some(KeyCode::KeyJ),
+            HidCodes::KeyboardKk => Some(KeyCode::KeyK),
+            HidCodes::KeyboardLl => Some(KeyCode::KeyL),
+            HidCodes::KeyboardMm => Some(KeyCode::KeyM),
+            HidCodes::KeyboardNn => Some(KeyCode::KeyN),
+            HidCodes::KeyboardOo => Some(KeyCode::KeyO),
+            HidCodes::KeyboardPp => Some(KeyCode::KeyP),
+            HidCodes::KeyboardQq => Some(KeyCode::KeyQ),
+            HidCodes::KeyboardRr => Some(KeyCode::KeyR),
+            HidCodes::KeyboardSs => Some(KeyCode::KeyS),
+            HidCodes::KeyboardTt => Some(KeyCode::KeyT),
+            HidCodes::KeyboardUu => Some(KeyCode::KeyU),
+            HidCodes::KeyboardVv => Some(KeyCode::KeyV),
+            HidCodes::KeyboardWw => Some(KeyCode::KeyW),
+            HidCodes::KeyboardXx => Some(KeyCode::KeyX),
+            HidCodes::KeyboardYy => Some(KeyCode::KeyY),
+            HidCodes::KeyboardZz => Some(KeyCode::KeyZ),
+            HidCodes::Keyboard1Exclamation => Some(KeyCode::Digit1),
+            HidCodes::Keyboard2At => Some(KeyCode::Digit2),
+            HidCodes::Keyboard3Hash => Some(KeyCode::Digit3),
+            HidCodes::Keyboard4Dollar => Some(KeyCode::Digit4),
+            HidCodes::Keyboard5Percent => Some(KeyCode::Digit5),
+            HidCodes::Keyboard6Caret => Some(KeyCode::Digit6),
+            HidCodes::Keyboard7Ampersand => Some(KeyCode::Digit7),
+            HidCodes::Keyboard8Asterisk => Some(KeyCode::Digit8),
+            HidCodes::Keyboard9OpenParens => Some(KeyCode::Digit9),
+            HidCodes::Keyboard0CloseParens => Some(KeyCode::Digit0),
+            HidCodes::KeyboardEnter => Some(KeyCode::Enter),
+            HidCodes::KeyboardEscape => Some(KeyCode::Escape),
+            HidCodes::KeyboardBackspace => Some(KeyCode::Backspace),
+            HidCodes::KeyboardTab => Some(KeyCode::Tab),
+            HidCodes::KeyboardSpacebar => Some(KeyCode::Space),
+            HidCodes::KeyboardDashUnderscore => Some(KeyCode::Minus),
+            HidCodes::KeyboardEqualPlus => Some(KeyCode::Equal),
+            HidCodes::KeyboardOpenBracketBrace => Some(KeyCode::BracketLeft),
+            HidCodes::KeyboardCloseBracketBrace => Some(KeyCode::BracketRight),
+            HidCodes::KeyboardBackslashBar => Some(KeyCode::Backslash),
+            HidCodes::KeyboardSemiColon => Some(KeyCode::Semicolon),
+            HidCodes::KeyboardSingleDoubleQuote => Some(KeyCode::Quote),
+            HidCodes::KeyboardBacktickTilde => Some(KeyCode::Backquote),
+            HidCodes::KeyboardCommaLess => Some(KeyCode::Comma),
+            HidCodes::KeyboardPeriodGreater => Some(KeyCode::Period),
+            HidCodes::KeyboardSlashQuestion => Some(KeyCode::Slash),
+            HidCodes::KeyboardCapsLock => Some(KeyCode::CapsLock),
+            HidCodes::KeyboardF1 => Some(KeyCode::F1),
+            HidCodes::KeyboardF2 => Some(KeyCode::F2),
+            HidCodes::KeyboardF3 => Some(KeyCode::F3),
+            HidCodes::KeyboardF4 => Some(KeyCode::F4),
+            HidCodes::KeyboardF5 => Some(KeyCode::F5),
+            HidCodes::KeyboardF6 => Some(KeyCode::F6),
+            HidCodes::KeyboardF7 => Some(KeyCode::F7),
+            HidCodes::KeyboardF8 => Some(KeyCode::F8),
+            HidCodes::KeyboardF9 => Some(KeyCode::F9),
+            HidCodes::KeyboardF10 => Some(KeyCode::F10),
+            HidCodes::KeyboardF11 => Some(KeyCode::F11),
+            HidCodes::KeyboardF12 => Some(KeyCode::F12),
+            HidCodes::KeyboardPrintScreen => Some(KeyCode::PrintScreen),
+            HidCodes::KeyboardScrollLock => Some(KeyCode::ScrollLock),
+            HidCodes::KeyboardPause => Some(KeyCode::Pause),
+            HidCodes::KeyboardInsert => Some(KeyCode::Insert),
+            HidCodes::KeyboardHome => Some(KeyCode::Home),
+            HidCodes::KeyboardPageUp => Some(KeyCode::PageUp),
+            HidCodes::KeyboardDelete => Some(KeyCode::Delete),
+            HidCodes::KeyboardEnd => Some(KeyCode::End),
+            HidCodes::KeyboardPageDown => Some(KeyCode::PageDown),
+            HidCodes::KeyboardRightArrow => Some(KeyCode::ArrowRight),
+            HidCodes::KeyboardLeftArrow => Some(KeyCode::ArrowLeft),
+            HidCodes::KeyboardDownArrow => Some(KeyCode::ArrowDown),
+            HidCodes::KeyboardUpArrow => Some(KeyCode::ArrowUp),
+            HidCodes::KeypadNumLock => Some(KeyCode::NumLock),
+            HidCodes::KeypadDivide => Some(KeyCode::NumpadDivide),
+            HidCodes::KeypadMultiply => Some(KeyCode::NumpadMultiply),
+            HidCodes::KeypadMinus => Some(KeyCode::NumpadSubtract),
+            HidCodes::KeypadPlus => Some(KeyCode::NumpadAdd),
+            HidCodes::KeypadEnter => Some(KeyCode::NumpadEnter),
+            HidCodes::Keypad1End => Some(KeyCode::Numpad1),
+            HidCodes::Keypad2DownArrow => Some(KeyCode::Numpad2),
+            HidCodes::Keypad3PageDown => Some(KeyCode::Numpad3),
+            HidCodes::Keypad4LeftArrow => Some(KeyCode::Numpad4),
+            HidCodes::Keypad5 => Some(KeyCode::Numpad5),
+            HidCodes::Keypad6RightArrow => Some(KeyCode::Numpad6),
+            HidCodes::Keypad7Home => Some(KeyCode::Numpad7),
+            HidCodes::Keypad8UpArrow => Some(KeyCode::Numpad8),
+            HidCodes::Keypad9PageUp => Some(KeyCode::Numpad9),
+            HidCodes::Keypad0Insert => Some(KeyCode::Numpad0),
+            HidCodes::KeypadPeriodDelete => Some(KeyCode::NumpadDecimal),
+            HidCodes::KeyboardNonUSSlash => Some(KeyCode::IntlBackslash),
+            HidCodes::KeyboardApplication => Some(KeyCode::ContextMenu),
+            HidCodes::KeyboardPower => Some(KeyCode::Power),
+            HidCodes::KeypadEqual => Some(KeyCode::NumpadEqual),
+            HidCodes::KeyboardF13 => Some(KeyCode::F13),
+            HidCodes::KeyboardF14 => Some(KeyCode::F14),
+            HidCodes::KeyboardF15 => Some(KeyCode::F15),
+            HidCodes::KeyboardF16 => Some(KeyCode::F16),
+            HidCodes::KeyboardF17 => Some(KeyCode::F17),
+            HidCodes::KeyboardF18 => Some(KeyCode::F18),
+            HidCodes::KeyboardF19 => Some(KeyCode::F19),
+            HidCodes::KeyboardF20 => Some(KeyCode::F20),
+            HidCodes::KeyboardF21 => Some(KeyCode::F21),
+            HidCodes::KeyboardF22 => Some(KeyCode::F22),
+            HidCodes::KeyboardF23 => Some(KeyCode::F23),
+            HidCodes::KeyboardF24 => Some(KeyCode::F24),
+            HidCodes::KeyboardExecute => Some(KeyCode::Open),
+            HidCodes::KeyboardHelp => Some(KeyCode::Help),
+            HidCodes::KeyboardSelect => Some(KeyCode::Select),
+            HidCodes::KeyboardAgain => Some(KeyCode::Again),
+            HidCodes::KeyboardUndo => Some(KeyCode::Undo),
+            HidCodes::KeyboardCut => Some(KeyCode::Cut),
+            HidCodes::KeyboardCopy => Some(KeyCode::Copy),
+            HidCodes::KeyboardPaste => Some(KeyCode::Paste),
+            HidCodes::KeyboardFind => Some(KeyCode::Find),
+            HidCodes::KeyboardMute => Some(KeyCode::AudioVolumeMute),
+            HidCodes::KeyboardVolumeUp => Some(KeyCode::AudioVolumeUp),
+            HidCodes::KeyboardVolumeDown => Some(KeyCode::AudioVolumeDown),
+            HidCodes::KeypadComma => Some(KeyCode::NumpadComma),
+            HidCodes::KeyboardInternational1 => Some(KeyCode::IntlRo),
+            HidCodes::KeyboardInternational2 => Some(KeyCode::KanaMode),
+            HidCodes::KeyboardInternational3 => Some(KeyCode::IntlYen),
+            HidCodes::KeyboardInternational4 => Some(KeyCode::Convert),
+            HidCodes::KeyboardInternational5 => Some(KeyCode::NonConvert),
+            HidCodes::KeyboardLANG1 => Some(KeyCode::Lang1),
+            HidCodes::KeyboardLANG2 => Some(KeyCode::Lang2),
+            HidCodes::KeyboardLANG3 => Some(KeyCode::Lang3),
+            HidCodes::KeyboardLANG4 => Some(KeyCode::Lang4),
+            HidCodes::KeyboardLANG5 => Some(KeyCode::Lang5),
+            HidCodes::KeyboardCancel => Some(KeyCode::Abort),
+            HidCodes::KeyboardCrSelProps => Some(KeyCode::Props),
+            HidCodes::KeypadOpenParens => Some(KeyCode::NumpadParenLeft),
+            HidCodes::KeypadCloseParens => Some(KeyCode::NumpadParenRight),
+            HidCodes::KeypadBackspace => Some(KeyCode::NumpadBackspace),
+            HidCodes::KeypadHash => Some(KeyCode::NumpadHash),
+            HidCodes::KeypadMemoryStore => Some(KeyCode::NumpadMemoryStore),
+            HidCodes::KeypadMemoryRecall => Some(KeyCode::NumpadMemoryRecall),
+            HidCodes::KeypadMemoryClear => Some(KeyCode::NumpadMemoryClear),
+            HidCodes::KeypadMemoryAdd => Some(KeyCode::NumpadMemoryAdd),
+            HidCodes::KeypadMemorySubtract => Some(KeyCode::NumpadMemorySubtract),
+            HidCodes::KeypadClear => Some(KeyCode::NumpadClear),
+            HidCodes::KeypadClearEntry => Some(KeyCode::NumpadClearEntry),
+            HidCodes::KeyboardLeftControl => Some(KeyCode::ControlLeft),
+            HidCodes::KeyboardLeftShift => Some(KeyCode::ShiftLeft),
+            HidCodes::KeyboardLeftAlt => Some(KeyCode::AltLeft),
+            HidCodes::KeyboardLeftGUI => Some(KeyCode::SuperLeft),
+            HidCodes::KeyboardRightControl => Some(KeyCode::ControlRight),
+            HidCodes::KeyboardRightShift => Some(KeyCode::ShiftRight),
+            HidCodes::KeyboardRightAlt => Some(KeyCode::AltRight),
+            HidCodes::KeyboardRightGUI => Some(KeyCode::SuperRight),
+            _ => None,
+        }
+    }
+
+    /// Name from the HID usage tables, e.g. "Keyboard a and A"
+    pub fn name(self) -> &'static str {
+        match self {
+            HidCodes::KeyboardErrorRollOver => "Keyboard ErrorRollOver",
+            HidCodes::KeyboardPOSTFail => "Keyboard POSTFail",
+            HidCodes::KeyboardErrorUndefined => "Keyboard ErrorUndefined",
+            HidCodes::KeyboardAa => "Keyboard a and A",
+            HidCodes::KeyboardBb => "Keyboard b and B",
+            HidCodes::KeyboardCc => "Keyboard c and C",
+            HidCodes::KeyboardDd => "Keyboard d and D",
+            HidCodes::KeyboardEe => "Keyboard e and E",
+            HidCodes::KeyboardFf => "Keyboard f and F",
+            HidCodes::KeyboardGg => "Keyboard g and G",
+            HidCodes::KeyboardHh => "Keyboard h and H",
+            HidCodes::KeyboardIi => "Keyboard i and I",
+            HidCodes::KeyboardJj => "Keyboard j and J",
+            HidCodes::KeyboardKk => "Keyboard k and K",
+            HidCodes::KeyboardLl => "Keyboard l and L",
+            HidCodes::KeyboardMm => "Keyboard m and M",
+            HidCodes::KeyboardNn => "Keyboard n and N",
+            HidCodes::KeyboardOo => "Keyboard o and O",
+            HidCodes::KeyboardPp => "Keyboard p and P",
+            HidCodes::KeyboardQq => "Keyboard q and Q",
+            HidCodes::KeyboardRr => "Keyboard r and R",
+            HidCodes::KeyboardSs => "Keyboard s and S",
+            HidCodes::KeyboardTt => "Keyboard t and T",
+            HidCodes::KeyboardUu => "Keyboard u and U",
+            HidCodes::KeyboardVv => "Keyboard v and V",
+            HidCodes::KeyboardWw => "Keyboard w and W",
+            HidCodes::KeyboardXx => "Keyboard x and X",
+            HidCodes::KeyboardYy => "Keyboard y and Y",
+            HidCodes::KeyboardZz => "Keyboard z and Z",
+            HidCodes::Keyboard1Exclamation => "Keyboard 1 and !",
+            HidCodes::Keyboard2At => "Keyboard 2 and @",
+            HidCodes::Keyboard3Hash => "Keyboard 3 and #",
+            HidCodes::Keyboard4Dollar => "Keyboard 4 and $",
+            HidCodes::Keyboard5Percent => "Keyboard 5 and %",
+            HidCodes::Keyboard6Caret => "Keyboard 6 and ^",
+            HidCodes::Keyboard7Ampersand => "Keyboard 7 and &",
+            HidCodes::Keyboard8Asterisk => "Keyboard 8 and *",
+            HidCodes::Keyboard9OpenParens => "Keyboard 9 and (",
+            HidCodes::Keyboard0CloseParens => "Keyboard 0 and )",
+            HidCodes::KeyboardEnter => "Keyboard Return (ENTER)",
+            HidCodes::KeyboardEscape => "Keyboard ESCAPE",
+            HidCodes::KeyboardBackspace => "Keyboard DELETE (Backspace)",
+            HidCodes::KeyboardTab => "Keyboard Tab",
+            HidCodes::KeyboardSpacebar => "Keyboard Spacebar",
+            HidCodes::KeyboardDashUnderscore => "Keyboard - and _",
+            HidCodes::KeyboardEqualPlus => "Keyboard = and +",
+            HidCodes::KeyboardOpenBracketBrace => "Keyboard [ and {",
+            HidCodes::KeyboardCloseBracketBrace => "Keyboard ] and }",
+            HidCodes::KeyboardBackslashBar => "Keyboard \\ and |",
+            HidCodes::KeyboardNonUSHash => "Keyboard Non-US # and ~",
+            HidCodes::KeyboardSemiColon => "Keyboard ; and :",
+            HidCodes::KeyboardSingleDoubleQuote => "Keyboard ' and \"",
+            HidCodes::KeyboardBacktickTilde => "Keyboard ` and ~",
+            HidCodes::KeyboardCommaLess => "Keyboard , and <",
+            HidCodes::KeyboardPeriodGreater => "Keyboard . and >",
+            HidCodes::KeyboardSlashQuestion => "Keyboard / and ?",
+            HidCodes::KeyboardCapsLock => "Keyboard Caps Lock",
+            HidCodes::KeyboardF1 => "Keyboard F1",
+            HidCodes::KeyboardF2 => "Keyboard F2",
+            HidCodes::KeyboardF3 => "Keyboard F3",
+            HidCodes::KeyboardF4 => "Keyboard F4",
+            HidCodes::KeyboardF5 => "Keyboard F5",
+            HidCodes::KeyboardF6 => "Keyboard F6",
+            HidCodes::KeyboardF7 => "Keyboard F7",
+            HidCodes::KeyboardF8 => "Keyboard F8",
+            HidCodes::KeyboardF9 => "Keyboard F9",
+            HidCodes::KeyboardF10 => "Keyboard F10",
+            HidCodes::KeyboardF11 => "Keyboard F11",
+            HidCodes::KeyboardF12 => "Keyboard F12",
+            HidCodes::KeyboardPrintScreen => "Keyboard PrintScreen",
+            HidCodes::KeyboardScrollLock => "Keyboard ScrollLock",
+            HidCodes::KeyboardPause => "Keyboard Pause",
+            HidCodes::KeyboardInsert => "Keyboard Insert",
+            HidCodes::KeyboardHome => "Keyboard Home",
+            HidCodes::KeyboardPageUp => "Keyboard PageUp",
+            HidCodes::KeyboardDelete => "Keyboard Delete Forward",
+            HidCodes::KeyboardEnd => "Keyboard End",
+            HidCodes::KeyboardPageDown => "Keyboard PageDown",
+            HidCodes::KeyboardRightArrow => "Keyboard RightArrow",
+            HidCodes::KeyboardLeftArrow => "Keyboard LeftArrow",
+            HidCodes::KeyboardDownArrow => "Keyboard DownArrow",
+            HidCodes::KeyboardUpArrow => "Keyboard UpArrow",
+            HidCodes::KeypadNumLock => "Keypad Num Lock and Clear",
+            HidCodes::KeypadDivide => "Keypad /",
+            HidCodes::KeypadMultiply => "Keypad *",
+            HidCodes::KeypadMinus => "Keypad -",
+            HidCodes::KeypadPlus => "Keypad +",
+            HidCodes::KeypadEnter => "Keypad ENTER",
+            HidCodes::Keypad1End => "Keypad 1 and End",
+            HidCodes::Keypad2DownArrow => "Keypad 2 and DownArrow",
+            HidCodes::Keypad3PageDown => "Keypad 3 and PageDown",
+            HidCodes::Keypad4LeftArrow => "Keypad 4 and LeftArrow",
+            HidCodes::Keypad5 => "Keypad 5",
+            HidCodes::Keypad6RightArrow => "Keypad 6 and RightArrow",
+            HidCodes::Keypad7Home => "Keypad 7 and Home",
+            HidCodes::Keypad8UpArrow => "Keypad 8 and UpArrow",
+            HidCodes::Keypad9PageUp => "Keypad 9 and PageUp",
+            HidCodes::Keypad0Insert => "Keypad 0 and Insert",
+            HidCodes::KeypadPeriodDelete => "Keypad . and Delete",
+            HidCodes::KeyboardNonUSSlash => "Keyboard Non-US \\ and |",
+            HidCodes::KeyboardApplication => "Keyboard Application",
+            HidCodes::KeyboardPower => "Keyboard Power",
+            HidCodes::KeypadEqual => "Keypad =",
+            HidCodes::KeyboardF13 => "Keyboard F13",
+            HidCodes::KeyboardF14 => "Keyboard F14",
+            HidCodes::KeyboardF15 => "Keyboard F15",
+            HidCodes::KeyboardF16 => "Keyboard F16",
+            HidCodes::KeyboardF17 => "Keyboard F17",
+            HidCodes::KeyboardF18 => "Keyboard F18",
+            HidCodes::KeyboardF19 => "Keyboard F19",
+            HidCodes::KeyboardF20 => "Keyboard F20",
+            HidCodes::KeyboardF21 => "Keyboard F21",
+            HidCodes::KeyboardF22 => "Keyboard F22",
+            HidCodes::KeyboardF23 => "Keyboard F23",
+            HidCodes::KeyboardF24 => "Keyboard F24",
+            HidCodes::KeyboardExecute => "Keyboard Execute",
+            HidCodes::KeyboardHelp => "Keyboard Help",
+            HidCodes::KeyboardMenu => "Keyboard Menu",
+            HidCodes::KeyboardSelect => "Keyboard Select",
+            HidCodes::KeyboardStop => "Keyboard Stop",
+            HidCodes::KeyboardAgain => "Keyboard Again",
+            HidCodes::KeyboardUndo => "Keyboard Undo",
+            HidCodes::KeyboardCut => "Keyboard Cut",
+            HidCodes::KeyboardCopy => "Keyboard Copy",
+            HidCodes::KeyboardPaste => "Keyboard Paste",
+            HidCodes::KeyboardFind => "Keyboard Find",
+            HidCodes::KeyboardMute => "Keyboard Mute",
+            HidCodes::KeyboardVolumeUp => "Keyboard Volume Up",
+            HidCodes::KeyboardVolumeDown => "Keyboard Volume Down",
+            HidCodes::KeyboardLockingCapsLock => "Keyboard Locking Caps Lock",
+            HidCodes::KeyboardLockingNumLock => "Keyboard Locking Num Lock",
+            HidCodes::KeyboardLockingScrollLock => "Keyboard Locking Scroll Lock",
+            HidCodes::KeypadComma => "Keypad Comma",
+            HidCodes::KeypadEqualSign => "Keypad Equal Sign",
+            HidCodes::KeyboardInternational1 => "Keyboard International1",
+            HidCodes::KeyboardInternational2 => "Keyboard International2",
+            HidCodes::KeyboardInternational3 => "Keyboard International3",
+            HidCodes::KeyboardInternational4 => "Keyboard International4",
+            HidCodes::KeyboardInternational5 => "Keyboard International5",
+            HidCodes::KeyboardInternational6 => "Keyboard International6",
+            HidCodes::KeyboardInternational7 => "Keyboard International7",
+            HidCodes::KeyboardInternational8 => "Keyboard International8",
+            HidCodes::KeyboardInternational9 => "Keyboard International9",
+            HidCodes::KeyboardLANG1 => "Keyboard LANG1",
+            HidCodes::KeyboardLANG2 => "Keyboard LANG2",
+            HidCodes::KeyboardLANG3 => "Keyboard LANG3",
+            HidCodes::KeyboardLANG4 => "Keyboard LANG4",
+            HidCodes::KeyboardLANG5 => "Keyboard LANG5",
+            HidCodes::KeyboardLANG6 => "Keyboard LANG6",
+            HidCodes::KeyboardLANG7 => "Keyboard LANG7",
+            HidCodes::KeyboardLANG8 => "Keyboard LANG8",
+            HidCodes::KeyboardLANG9 => "Keyboard LANG9",
+            HidCodes::KeyboardAlternateErase => "Keyboard Alternate Erase",
+            HidCodes::KeyboardSysReqAttention => "Keyboard SysReq/Attention",
+            HidCodes::KeyboardCancel => "Keyboard Cancel",
+            HidCodes::KeyboardClear => "Keyboard Clear",
+            HidCodes::KeyboardPrior => "Keyboard Prior",
+            HidCodes::KeyboardReturn => "Keyboard Return",
+            HidCodes::KeyboardSeparator => "Keyboard Separator",
+            HidCodes::KeyboardOut => "Keyboard Out",
+            HidCodes::KeyboardOper => "Keyboard Oper",
+            HidCodes::KeyboardClearAgain => "Keyboard Clear/Again",
+            HidCodes::KeyboardCrSelProps => "Keyboard CrSel/Props",
+            HidCodes::KeyboardExSel => "Keyboard ExSel",
+            HidCodes::Keypad00 => "Keypad 00",
+            HidCodes::Keypad000 => "Keypad 000",
+            HidCodes::ThousandsSeparator => "Thousands Separator",
+            HidCodes::DecimalSeparator => "Decimal Separator",
+            HidCodes::CurrencyUnit => "Currency Unit",
+            HidCodes::CurrencySubunit => "Currency Sub-unit",
+            HidCodes::KeypadOpenParens => "Keypad (",
+            HidCodes::KeypadCloseParens => "Keypad )",
+            HidCodes::KeypadOpenBrace => "Keypad {",
+            HidCodes::KeypadCloseBrace => "Keypad }",
+            HidCodes::KeypadTab => "Keypad Tab",
+            HidCodes::KeypadBackspace => "Keypad Backspace",
+            HidCodes::KeypadA => "Keypad A",
+            HidCodes::KeypadB => "Keypad B",
+            HidCodes::KeypadC => "Keypad C",
+            HidCodes::KeypadD => "Keypad D",
+            HidCodes::KeypadE => "Keypad E",
+            HidCodes::KeypadF => "Keypad F",
+            HidCodes::KeypadBitwiseXor => "Keypad XOR",
+            HidCodes::KeypadLogicalXor => "Keypad ^",
+            HidCodes::KeypadModulo => "Keypad %",
+            HidCodes::KeypadLeftShift => "Keypad <",
+            HidCodes::KeypadRightShift => "Keypad >",
+            HidCodes::KeypadBitwiseAnd => "Keypad &",
+            HidCodes::KeypadLogicalAnd => "Keypad &&",
+            HidCodes::KeypadBitwiseOr => "Keypad |",
+            HidCodes::KeypadLogicalOr => "Keypad ||",
+            HidCodes::KeypadColon => "Keypad :",
+            HidCodes::KeypadHash => "Keypad #",
+            HidCodes::KeypadSpace => "Keypad Space",
+            HidCodes::KeypadAt => "Keypad @",
+            HidCodes::KeypadExclamation => "Keypad !",
+            HidCodes::KeypadMemoryStore => "Keypad Memory Store",
+            HidCodes::KeypadMemoryRecall => "Keypad Memory Recall",
+            HidCodes::KeypadMemoryClear => "Keypad Memory Clear",
+            HidCodes::KeypadMemoryAdd => "Keypad Memory Add",
+            HidCodes::KeypadMemorySubtract => "Keypad Memory Subtract",
+            HidCodes::KeypadMemoryMultiply => "Keypad Memory Multiply",
+            HidCodes::KeypadMemoryDivide => "Keypad Memory Divide",
+            HidCodes::KeypadPositiveNegative => "Keypad +/-",
+            HidCodes::KeypadClear => "Keypad Clear",
+            HidCodes::KeypadClearEntry => "Keypad Clear Entry",
+            HidCodes::KeypadBinary => "Keypad Binary",
+            HidCodes::KeypadOctal => "Keypad Octal",
+            HidCodes::KeypadDecimal => "Keypad Decimal",
+            HidCodes::KeypadHexadecimal => "Keypad Hexadecimal",
+            HidCodes::KeyboardLeftControl => "Keyboard LeftControl",
+            HidCodes::KeyboardLeftShift => "Keyboard LeftShift",
+            HidCodes::KeyboardLeftAlt => "Keyboard LeftAlt",
+            HidCodes::KeyboardLeftGUI => "Keyboard LeftGUI",
+            HidCodes::KeyboardRightControl => "Keyboard RightControl",
+            HidCodes::KeyboardRightShift => "Keyboard RightShift",
+            HidCodes::KeyboardRightAlt => "Keyboard RightAlt",
+            HidCodes::KeyboardRightGUI => "Keyboard RightGUI",
+            HidCodes::Reserved => "Reserved",
+            HidCodes::Layer0 => "Layer 0",
+            HidCodes::Layer1 => "Layer 1",
+            HidCodes::Layer2 => "Layer 2",
+            HidCodes::Layer3 => "Layer 3",
+            HidCodes::Layer4 => "Layer 4",
+            HidCodes::Layer5 => "Layer 5",
+            HidCodes::Layer6 => "Layer 6",
+            HidCodes::Layer7 => "Layer 7",
+            HidCodes::Layer8 => "Layer 8",
+            HidCodes::Layer9 => "Layer 9",
+            HidCodes::Layer10 => "Layer 10",
+            HidCodes::MouseLeftClick => "Mouse Left Click",
+            HidCodes::MouseRightClick => "Mouse Right Click",
+            HidCodes::MouseMiddleClick => "Mouse Middle Click",
+            HidCodes::Mouse4 => "Mouse 4",
+            HidCodes::Mouse5 => "Mouse 5",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use winit::event::ElementState;
+
+    use super::*;
+    use crate::codes::{ScanCode, tests::ALL_KEYS};
+
+    fn all() -> Vec<HidCodes> {
+        (0..=u8::MAX)
+            .filter_map(|value| HidCodes::try_from(value).ok())
+            .collect()
+    }
+
+    fn to_hid(code: KeyCode) -> Option<HidCodes> {
+        ScanCode::new(code, ElementState::Pressed).to_hid()
+    }
+
+    #[test]
+    fn values_round_trip() {
+        // Every variant, so one missing from `try_from` is caught
+        assert_eq!(all().len(), 235);
+        for code in all() {
+            assert_eq!(HidCodes::try_from(u8::from(code)), Ok(code));
+        }
+        assert_eq!(HidCodes::try_from(0x00), Err(0x00));
+        assert_eq!(HidCodes::try_from(0xFF), Err(0xFF));
+    }
+
+    #[test]
+    fn key_codes_invert_to_hid() {
+        for code in all() {
+            if let Some(key) = code.to_key_code() {
+                assert_eq!(to_hid(key), Some(code), "{:?} gives back {:?}", code, key);
+            }
+        }
+        for key in ALL_KEYS {
+            if let Some(code) = to_hid(key) {
+                assert!(code.to_key_code().is_some(), "{:?} has no key", code);
+            }
+        }
+    }
+
+    #[test]
+    fn names_are_distinct() {
+        let names: HashSet<_> = all().into_iter().map(HidCodes::name).collect();
+        assert_eq!(names.len(), all().len());
+    }
+}
@@ -0,0 +1,106 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use winit::{
+    event::{ElementState, MouseButton},
+    keyboard::KeyCode,
+};
+
+use crate::codes::{HidEvent, MouseButtons, ScanCode};
+
+/// Keys and mouse buttons held down after a stream of [`HidEvent`]s
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputState {
+    keys: HashSet<KeyCode>,
+    buttons: HashSet<MouseButton>,
+}
+
+impl InputState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Updates the held keys and buttons, everything that isn't a key or button is ignored
+    pub fn apply(&mut self, hid_event: &HidEvent) {
+        match hid_event {
+            HidEvent::Key(scan_code) => match scan_code.dir {
+                ElementState::Pressed => self.keys.insert(scan_code.code()),
+                ElementState::Released => self.keys.remove(&scan_code.code()),
+            },
+            HidEvent::MouseButton(mouse_buttons) => match mouse_buttons.dir {
+                ElementState::Pressed => self.buttons.insert(mouse_buttons.button()),
+                ElementState::Released => self.buttons.remove(&mouse_buttons.button()),
+            },
+            _ => false,
+        };
+    }
+
+    pub fn keys(&self) -> &HashSet<KeyCode> {
+        &self.keys
+    }
+
+    pub fn buttons(&self) -> &HashSet<MouseButton> {
+        &self.buttons
+    }
+
+    pub fn is_key_held(&self, code: KeyCode) -> bool {
+        self.keys.contains(&code)
+    }
+
+    pub fn is_button_held(&self, button: MouseButton) -> bool {
+        self.buttons.contains(&button)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty() && self.buttons.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.keys.clear();
+        self.buttons.clear();
+    }
+
+    /// Events that take `self` to `target`: releases of what `target` doesn't hold,
+    /// then presses of what it holds that `self` doesn't
+    pub fn diff(&self, target: &InputState) -> Vec<HidEvent> {
+        let released = ElementState::Released;
+        let pressed = ElementState::Pressed;
+        let mut events = Vec::new();
+        events.extend(
+            self.keys
+                .difference(&target.keys)
+                .map(|&code| HidEvent::Key(ScanCode::new(code, released))),
+        );
+        events.extend(
+            self.buttons
+                .difference(&target.buttons)
+                .map(|&button| HidEvent::MouseButton(MouseButtons::new(button, released))),
+        );
+        events.extend(
+            target
+                .keys
+                .difference(&self.keys)
+                .map(|&code| HidEvent::Key(ScanCode::new(code, pressed))),
+        );
+        events.extend(
+            target
+                .buttons
+                .difference(&self.buttons)
+                .map(|&button| HidEvent::MouseButton(MouseButtons::new(button, pressed))),
+        );
+        events
+    }
+
+    /// Events that press, or release, everything held
+    pub fn events(&self, dir: ElementState) -> impl Iterator<Item = HidEvent> + '_ {
+        let keys = self
+            .keys
+            .iter()
+            .map(move |&code| HidEvent::Key(ScanCode::new(code, dir)));
+        let buttons = self
+            .buttons
+            .iter()
+            .map(move |&button| HidEvent::MouseButton(MouseButtons::new(button, dir)));
+        keys.chain(buttons)
+    }
+}
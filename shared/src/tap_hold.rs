@@ -0,0 +1,258 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    time::{Duration, Instant},
+};
+
+use serde::Deserialize;
+use winit::{event::ElementState, keyboard::KeyCode};
+
+use crate::codes::{HidEvent, ScanCode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct DualRole {
+    /// Sent when the key is tapped
+    pub tap: KeyCode,
+    /// Held for as long as the key is, once it counts as held. A layer switch from the
+    /// [`Keymap`](crate::layers::Keymap) makes this a layer key
+    pub hold: KeyCode,
+}
+
+/// Keys that do one thing on tap and another on hold, e.g.
+///
+/// ```toml
+/// term = 180
+/// permissive_hold = true
+///
+/// [keys]
+/// CapsLock = { tap = "Escape", hold = "ControlLeft" }
+/// Space = { tap = "Space", hold = "F24" }
+/// ```
+///
+/// with `F24 = { layer = 1 }` among the keymap's switches to hold layer 1 on Space
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DualRoleKeys {
+    /// Milliseconds a key has to be held before it counts as held
+    pub term: u64,
+    /// Count as held as soon as another key is pressed and released within the term
+    pub permissive_hold: bool,
+    /// Count as held as soon as another key is pressed within the term
+    pub hold_on_other_key_press: bool,
+    pub keys: HashMap<KeyCode, DualRole>,
+}
+
+impl Default for DualRoleKeys {
+    fn default() -> Self {
+        Self {
+            term: 200,
+            permissive_hold: false,
+            hold_on_other_key_press: false,
+            keys: HashMap::new(),
+        }
+    }
+}
+
+/// A dual-role key that's down but not yet known to be a tap or a hold
+#[derive(Debug, Clone, Copy)]
+struct Pending {
+    code: KeyCode,
+    since: Instant,
+}
+
+/// Runs [`DualRoleKeys`]. Keys and buttons that come while a key is undecided are held
+/// back and sent after whatever it turns out to be, so the target sees them in order
+#[derive(Debug, Clone)]
+pub struct TapHold {
+    keys: DualRoleKeys,
+    pending: Option<Pending>,
+    buffer: VecDeque<(HidEvent, Instant)>,
+    // Dual-role keys that turned out held, released as their hold key
+    holding: HashSet<KeyCode>,
+}
+
+fn key(code: KeyCode, dir: ElementState) -> HidEvent {
+    HidEvent::Key(ScanCode::new(code, dir))
+}
+
+impl TapHold {
+    pub fn new(keys: DualRoleKeys) -> Self {
+        Self {
+            keys,
+            pending: None,
+            buffer: VecDeque::new(),
+            holding: HashSet::new(),
+        }
+    }
+
+    /// When the undecided key, if any, turns into a hold
+    pub fn deadline(&self) -> Option<Instant> {
+        self.pending
+            .map(|pending| pending.since + Duration::from_millis(self.keys.term))
+    }
+
+    /// Forgets undecided and held keys, for when capture starts over
+    pub fn release_all(&mut self) {
+        self.pending = None;
+        self.buffer.clear();
+        self.holding.clear();
+    }
+
+    /// What `hid_event`, happening at `now`, lets out
+    pub fn apply(&mut self, hid_event: HidEvent, now: Instant) -> Vec<HidEvent> {
+        let mut out = Vec::new();
+        self.process(hid_event, now, &mut out);
+        out
+    }
+
+    /// Resolves the undecided key as held if the term ran out by `now`
+    pub fn timeout(&mut self, now: Instant) -> Vec<HidEvent> {
+        let mut out = Vec::new();
+        if self.deadline().is_some_and(|deadline| deadline <= now) {
+            self.resolve(true, &mut out);
+        }
+        out
+    }
+
+    fn process(&mut self, hid_event: HidEvent, at: Instant, out: &mut Vec<HidEvent>) {
+        let Some(pending) = self.pending else {
+            return self.start(hid_event, at, out);
+        };
+        // Before anything else, so a release that beats the timer but not the term still
+        // counts as a hold
+        if self.deadline().is_some_and(|deadline| deadline <= at) {
+            self.resolve(true, out);
+            return self.process(hid_event, at, out);
+        }
+        let pressed = match hid_event {
+            HidEvent::Key(scan_code) if scan_code.code() == pending.code => {
+                // Autorepeat of the undecided key itself
+                if scan_code.dir == ElementState::Pressed {
+                    return;
+                }
+                let tap = self.keys.keys[&pending.code].tap;
+                self.resolve(false, out);
+                out.push(key(tap, ElementState::Released));
+                return;
+            }
+            HidEvent::Key(scan_code) => scan_code.dir == ElementState::Pressed,
+            HidEvent::MouseButton(mouse_buttons) => mouse_buttons.dir == ElementState::Pressed,
+            // Only keys and buttons need to stay in order with the undecided key
+            _ => return out.push(hid_event),
+        };
+        // A release whose press was held back too means a whole tap of another key
+        let interrupted = if pressed {
+            self.keys.hold_on_other_key_press
+        } else {
+            self.keys.permissive_hold
+                && self
+                    .buffer
+                    .iter()
+                    .any(|(held, _)| releases(&hid_event, held))
+        };
+        self.buffer.push_back((hid_event, at));
+        if interrupted {
+            self.resolve(true, out);
+        }
+    }
+
+    /// Handles an event while no key is undecided
+    fn start(&mut self, hid_event: HidEvent, at: Instant, out: &mut Vec<HidEvent>) {
+        let HidEvent::Key(scan_code) = hid_event else {
+            return out.push(hid_event);
+        };
+        let code = scan_code.code();
+        let Some(role) = self.keys.keys.get(&code) else {
+            return out.push(hid_event);
+        };
+        match scan_code.dir {
+            // Autorepeat of a key already held
+            ElementState::Pressed if self.holding.contains(&code) => (),
+            ElementState::Pressed => self.pending = Some(Pending { code, since: at }),
+            ElementState::Released => {
+                if self.holding.remove(&code) {
+                    out.push(key(role.hold, ElementState::Released));
+                }
+            }
+        }
+    }
+
+    /// Presses the undecided key's tap or hold key, then lets out what was held back
+    fn resolve(&mut self, hold: bool, out: &mut Vec<HidEvent>) {
+        let Some(pending) = self.pending.take() else {
+            return;
+        };
+        let role = self.keys.keys[&pending.code];
+        if hold {
+            self.holding.insert(pending.code);
+            out.push(key(role.hold, ElementState::Pressed));
+        } else {
+            out.push(key(role.tap, ElementState::Pressed));
+        }
+        // These may start another undecided key, which holds back the rest again
+        for (hid_event, at) in std::mem::take(&mut self.buffer) {
+            self.process(hid_event, at, out);
+        }
+    }
+}
+
+/// True if `release` lets go of what `press` pressed
+fn releases(release: &HidEvent, press: &HidEvent) -> bool {
+    match (release, press) {
+        (HidEvent::Key(release), HidEvent::Key(press)) => {
+            press.dir == ElementState::Pressed && release.code() == press.code()
+        }
+        (HidEvent::MouseButton(release), HidEvent::MouseButton(press)) => {
+            press.dir == ElementState::Pressed && release.button() == press.button()
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn caps_lock() -> TapHold {
+        let mut keys = DualRoleKeys::default();
+        let role = DualRole {
+            tap: KeyCode::Escape,
+            hold: KeyCode::ControlLeft,
+        };
+        keys.keys.insert(KeyCode::CapsLock, role);
+        TapHold::new(keys)
+    }
+
+    #[test]
+    fn release_within_the_term_taps() {
+        let mut tap_hold = caps_lock();
+        let start = Instant::now();
+        let press = key(KeyCode::CapsLock, ElementState::Pressed);
+        assert!(tap_hold.apply(press, start).is_empty());
+        let release = key(KeyCode::CapsLock, ElementState::Released);
+        assert_eq!(
+            tap_hold.apply(release, start + Duration::from_millis(100)),
+            [
+                key(KeyCode::Escape, ElementState::Pressed),
+                key(KeyCode::Escape, ElementState::Released),
+            ]
+        );
+    }
+
+    #[test]
+    fn release_after_the_term_holds() {
+        let mut tap_hold = caps_lock();
+        let start = Instant::now();
+        let press = key(KeyCode::CapsLock, ElementState::Pressed);
+        assert!(tap_hold.apply(press, start).is_empty());
+        // The release got in before the timer did
+        let release = key(KeyCode::CapsLock, ElementState::Released);
+        assert_eq!(
+            tap_hold.apply(release, start + Duration::from_millis(250)),
+            [
+                key(KeyCode::ControlLeft, ElementState::Pressed),
+                key(KeyCode::ControlLeft, ElementState::Released),
+            ]
+        );
+        assert_eq!(tap_hold.deadline(), None);
+    }
+}
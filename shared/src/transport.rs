@@ -0,0 +1,285 @@
+use std::{
+    collections::VecDeque,
+    io,
+    sync::{Arc, Mutex},
+};
+
+use hidapi::{HidApi, HidDevice, MAX_REPORT_DESCRIPTOR_SIZE};
+use tokio::sync::Notify;
+#[cfg(target_os = "linux")]
+use tokio::{io::unix::AsyncFd, runtime::Handle, task::JoinHandle};
+
+/// How a [`HidEmulator`](crate::emulator::HidEmulator) finds and talks to the dongle
+pub trait HidTransport: Send + 'static {
+    type Device: HidWrite + Send + 'static;
+
+    /// Looks for the interface and opens it. `None` if it isn't connected or can't be
+    /// opened yet, errors are kept for when the lookup itself is broken
+    fn open(&mut self, vid: u16, pid: u16, i_num: i32) -> io::Result<Option<Self::Device>>;
+
+    /// Notified whenever a device may have been plugged in, so the search doesn't have to
+    /// wait for its next poll. `None` leaves the search to polling alone
+    fn hotplug(&mut self) -> Option<Arc<Notify>> {
+        None
+    }
+}
+
+pub trait HidWrite {
+    /// Writes a single report, the first byte being the report id
+    fn write(&self, buf: &[u8]) -> io::Result<usize>;
+
+    /// Raw report descriptor of the opened interface
+    fn report_descriptor(&self) -> io::Result<Vec<u8>>;
+
+    /// Takes the next input report the device sent, without blocking. 0 if there's none
+    fn read(&self, buf: &mut [u8]) -> io::Result<usize>;
+}
+
+/// Default transport that goes through hidapi
+#[derive(Default)]
+pub struct HidApiTransport {
+    api: Option<HidApi>,
+    #[cfg(target_os = "linux")]
+    monitor: Option<HotplugMonitor>,
+}
+
+impl HidApiTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl HidTransport for HidApiTransport {
+    type Device = HidDevice;
+
+    fn open(&mut self, vid: u16, pid: u16, i_num: i32) -> io::Result<Option<HidDevice>> {
+        let api = match &mut self.api {
+            Some(api) => api,
+            None => self.api.insert(HidApi::new().map_err(io::Error::other)?),
+        };
+        api.refresh_devices().map_err(io::Error::other)?;
+        let Some(info) = api.device_list().find(|dev| {
+            dev.vendor_id() == vid && dev.product_id() == pid && dev.interface_number() == i_num
+        }) else {
+            return Ok(None);
+        };
+        Ok(info.open_device(api).ok())
+    }
+
+    #[cfg(target_os = "linux")]
+    fn hotplug(&mut self) -> Option<Arc<Notify>> {
+        if self.monitor.is_none() {
+            match HotplugMonitor::new() {
+                Ok(monitor) => self.monitor = Some(monitor),
+                Err(e) => println!("No udev monitor, polling for the device instead: {}", e),
+            }
+        }
+        self.monitor.as_ref().map(|monitor| monitor.notify.clone())
+    }
+}
+
+/// Watches udev for new hidraw nodes. The watching task lives as long as the monitor
+#[cfg(target_os = "linux")]
+struct HotplugMonitor {
+    notify: Arc<Notify>,
+    task: JoinHandle<()>,
+}
+
+#[cfg(target_os = "linux")]
+impl HotplugMonitor {
+    fn new() -> io::Result<Self> {
+        let socket = udev::MonitorBuilder::new()?
+            .match_subsystem("hidraw")?
+            .listen()?;
+        let socket = AsyncFd::new(socket)?;
+        let notify = Arc::new(Notify::new());
+        let task = Handle::current().spawn(hotplug_loop(socket, notify.clone()));
+        Ok(Self { notify, task })
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for HotplugMonitor {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(target_os = "linux")]
+async fn hotplug_loop(mut socket: AsyncFd<udev::MonitorSocket>, notify: Arc<Notify>) {
+    loop {
+        let Ok(mut guard) = socket.readable_mut().await else {
+            return;
+        };
+        // Drain everything that's queued, not just up to the first add
+        let added = guard
+            .get_inner()
+            .iter()
+            .filter(|event| event.event_type() == udev::EventType::Add)
+            .count();
+        guard.clear_ready();
+        if added > 0 {
+            notify.notify_one();
+        }
+    }
+}
+
+impl HidWrite for HidDevice {
+    fn write(&self, buf: &[u8]) -> io::Result<usize> {
+        HidDevice::write(self, buf).map_err(io::Error::other)
+    }
+
+    fn report_descriptor(&self) -> io::Result<Vec<u8>> {
+        let mut buf = vec![0u8; MAX_REPORT_DESCRIPTOR_SIZE];
+        let len = self
+            .get_report_descriptor(&mut buf)
+            .map_err(io::Error::other)?;
+        buf.truncate(len);
+        Ok(buf)
+    }
+
+    fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.read_timeout(buf, 0).map_err(io::Error::other)
+    }
+}
+
+#[derive(Default)]
+struct MemoryState {
+    attached: bool,
+    failing: bool,
+    // Bumped whenever the device goes away so handles from an earlier enumeration
+    // keep failing even after it comes back
+    generation: u64,
+    opens: usize,
+    reports: Vec<Vec<u8>>,
+    inputs: VecDeque<Vec<u8>>,
+    descriptor: Vec<u8>,
+}
+
+/// In-memory transport for exercising [`HidEmulator`](crate::emulator::HidEmulator)
+/// without a dongle plugged in.
+///
+/// Clones share the same fake device, so keep one around to drive it after handing
+/// another to the emulator.
+#[derive(Clone, Default)]
+pub struct MemoryTransport {
+    state: Arc<Mutex<MemoryState>>,
+    hotplug: Arc<Notify>,
+}
+
+impl MemoryTransport {
+    /// Starts with no device attached
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes the device show up, waking the search like a udev event would
+    pub fn attach(&self) {
+        self.state.lock().unwrap().attached = true;
+        self.hotplug.notify_one();
+    }
+
+    /// Unplugs the device. Writes through handles that were already opened fail from
+    /// now on, even if the device is attached again
+    pub fn detach(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.attached = false;
+        state.generation += 1;
+    }
+
+    /// Makes every write fail while the device stays enumerable
+    pub fn set_failing(&self, failing: bool) {
+        self.state.lock().unwrap().failing = failing;
+    }
+
+    /// Report descriptor handed out by the fake device. Empty unless set
+    pub fn set_descriptor(&self, descriptor: Vec<u8>) {
+        self.state.lock().unwrap().descriptor = descriptor;
+    }
+
+    /// Queues an input report for the emulator to read, like the dongle sending one
+    pub fn send_input(&self, report: Vec<u8>) {
+        self.state.lock().unwrap().inputs.push_back(report);
+    }
+
+    pub fn is_attached(&self) -> bool {
+        self.state.lock().unwrap().attached
+    }
+
+    /// Number of times the device has been opened
+    pub fn opens(&self) -> usize {
+        self.state.lock().unwrap().opens
+    }
+
+    /// Every report written so far, in order
+    pub fn reports(&self) -> Vec<Vec<u8>> {
+        self.state.lock().unwrap().reports.clone()
+    }
+
+    /// Returns the reports written so far and forgets them
+    pub fn take_reports(&self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut self.state.lock().unwrap().reports)
+    }
+}
+
+impl HidTransport for MemoryTransport {
+    type Device = MemoryDevice;
+
+    fn open(&mut self, _vid: u16, _pid: u16, _i_num: i32) -> io::Result<Option<MemoryDevice>> {
+        let mut state = self.state.lock().unwrap();
+        if !state.attached {
+            return Ok(None);
+        }
+        state.opens += 1;
+        Ok(Some(MemoryDevice {
+            state: self.state.clone(),
+            generation: state.generation,
+        }))
+    }
+
+    fn hotplug(&mut self) -> Option<Arc<Notify>> {
+        Some(self.hotplug.clone())
+    }
+}
+
+pub struct MemoryDevice {
+    state: Arc<Mutex<MemoryState>>,
+    generation: u64,
+}
+
+impl MemoryDevice {
+    fn connected(&self) -> io::Result<std::sync::MutexGuard<'_, MemoryState>> {
+        let state = self.state.lock().unwrap();
+        if !state.attached || state.generation != self.generation {
+            return Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "device detached",
+            ));
+        }
+        Ok(state)
+    }
+}
+
+impl HidWrite for MemoryDevice {
+    fn write(&self, buf: &[u8]) -> io::Result<usize> {
+        let mut state = self.connected()?;
+        if state.failing {
+            return Err(io::Error::other("write failed"));
+        }
+        state.reports.push(buf.to_vec());
+        Ok(buf.len())
+    }
+
+    fn report_descriptor(&self) -> io::Result<Vec<u8>> {
+        Ok(self.state.lock().unwrap().descriptor.clone())
+    }
+
+    fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some(report) = self.connected()?.inputs.pop_front() else {
+            return Ok(0);
+        };
+        let len = report.len().min(buf.len());
+        buf[..len].copy_from_slice(&report[..len]);
+        Ok(len)
+    }
+}